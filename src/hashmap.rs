@@ -10,9 +10,15 @@ use crate::Map;
 /// HashMap
 pub struct HashMap<K: Eq + Hash, V>(XHashMap<K, V>);
 
-impl<K: Hash + Eq, V> Map for HashMap<K, V>{
+impl<K: Hash + Eq + Clone, V> Map for HashMap<K, V>{
     type Key = K;
 	type Val = V;
+    type Iter<'a> = Iter<'a, K, V> where Self: 'a;
+
+    #[inline]
+    fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.0.iter())
+    }
 
     #[inline]
     fn len(&self) -> usize{
@@ -44,6 +50,15 @@ impl<K: Hash + Eq, V> Map for HashMap<K, V>{
         self.0.get_mut(key)
     }
 
+    #[inline]
+    fn get2_mut(&mut self, a: &Self::Key, b: &Self::Key) -> (Option<&mut Self::Val>, Option<&mut Self::Val>) {
+        if a == b {
+            return (None, None);
+        }
+        let [a, b] = self.0.get_disjoint_mut([a, b]);
+        (a, b)
+    }
+
     #[inline]
     unsafe fn get_unchecked(&self, key: &Self::Key) -> &Self::Val {
         self.0.get(key).unwrap()
@@ -68,14 +83,131 @@ impl<K: Hash + Eq, V> Map for HashMap<K, V>{
     fn remove(&mut self, key: &Self::Key) -> Option<Self::Val> {
         self.0.remove(key)
 	}
+
+    #[inline]
+    fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &mut V {
+        self.0.entry(key).or_insert_with(f)
+    }
+
+    #[inline]
+    fn extend(&mut self, iter: impl IntoIterator<Item = (K, V)>) {
+        self.0.extend(iter)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    #[inline]
+    fn retain(&mut self, f: impl FnMut(&Self::Key, &mut Self::Val) -> bool) {
+        self.0.retain(f)
+    }
 	
 	fn with_capacity(_capacity: usize) -> Self {
 		Self::default()
 	}
 }
 
+/// HashMap的只读迭代器，产出`(key, &value)`
+/// `Map::Iter`按值产出键，因此每产出一项都会克隆一次键，键的克隆代价较大时应注意
+pub struct Iter<'a, K, V>(std::collections::hash_map::Iter<'a, K, V>);
+
+impl<'a, K: Clone, V> Iterator for Iter<'a, K, V> {
+    type Item = (K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, &'a V)> {
+        self.0.next().map(|(k, v)| (k.clone(), v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
 impl<K: Hash + Eq, V> Default for HashMap<K, V> {
     fn default() -> Self{
         HashMap(XHashMap::default())
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_iter(){
+    let mut map: HashMap<String, u32> = HashMap::default();
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+    let mut r: Vec<(String, u32)> = map.iter().map(|(k, v)| (k, *v)).collect();
+    r.sort();
+    assert_eq!(r, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+    assert_eq!(map.iter().size_hint(), (2, Some(2)));
+}
+
+#[test]
+fn test_get2_mut(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    map.insert(1, 10);
+    map.insert(2, 20);
+    let (a, b) = map.get2_mut(&1, &2);
+    std::mem::swap(a.unwrap(), b.unwrap());
+    assert_eq!(map.get(&1), Some(&20));
+    assert_eq!(map.get(&2), Some(&10));
+    let (a, b) = map.get2_mut(&1, &3);
+    assert_eq!((a, b), (Some(&mut 20), None));
+    assert_eq!(map.get2_mut(&1, &1), (None, None));
+}
+
+#[test]
+fn test_get_or_insert_with(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    *map.get_or_insert_with(1, || 10) += 1;
+    assert_eq!(map.get(&1), Some(&11));
+    let mut called = false;
+    assert_eq!(*map.get_or_insert_with(1, || { called = true; 0 }), 11);
+    assert!(!called);
+}
+
+#[test]
+fn test_extend(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    map.insert(1, 0);
+    Map::extend(&mut map, (1..4).map(|i| (i, i * 10)));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&1), Some(&10));
+    assert_eq!(map.get(&3), Some(&30));
+}
+
+#[test]
+fn test_reserve(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    map.reserve(100);
+    assert!(map.capacity() >= 100);
+}
+
+#[test]
+fn test_clear(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    map.insert(1, 10);
+    map.insert(2, 20);
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get(&1), None);
+}
+
+#[test]
+fn test_retain(){
+    let mut map: HashMap<u32, u32> = HashMap::default();
+    Map::extend(&mut map, (1..11).map(|i| (i, i)));
+    map.retain(|k, v| {
+        *v += 1;
+        k % 2 == 0
+    });
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.get(&2), Some(&3));
+}
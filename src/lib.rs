@@ -2,37 +2,200 @@
 //! * 定义了一个triat：`Map`
 //! * 定义了一个数据结构：`VecMap`，并为`VecMap`实现了`Map<K=usize,V=T>`
 //! * 定义了数据结构：`HashMap`，并为`HashMap`实现了`Map`
+//! * 可选特性`serde`：为`VecMap`、`SmallVecMap`实现`Serialize`、`Deserialize`，序列化为`key -> value`的映射
+//! * 可选特性`borsh`：为`SmallVecMap`实现`BorshSerialize`、`BorshDeserialize`，编码为长度加按键升序排列的`(key, value)`
+//! * 可选特性`rkyv`：`SmallVecMap`（键为`u32`时）归档为按键排序的`ArchivedBTreeMap`，可不反序列化直接按键查询
+//! * 可选特性`rayon`：为`SmallVecMap`提供并行迭代器`par_iter`、`par_iter_mut`
 
 #![feature(rustc_private)]
 // #![feature(integer_atomics)]
-#![feature(box_patterns)]
+// #![feature(box_patterns)]
 // #![feature(core_intrinsics)]
 // #![feature(generators, generator_trait)]
-#![feature(associated_type_defaults)]
-#![feature(exclusive_range_pattern)]
-#![feature(trait_alias)]
+// #![feature(associated_type_defaults)]
+// #![feature(trait_alias)]
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
 #[warn(unreachable_patterns)]
-
 #[allow(dead_code,unused_variables,non_snake_case,unused_parens,unused_assignments,unused_unsafe,unused_imports)]
-
-
 pub mod vecmap;
 pub mod hashmap;
 pub mod smallvecmap;
+
+pub use smallvecmap::DefaultSmallVecMap;
+
 /// Map接口定义
 pub trait Map{
-	type Key;
+    /// 键需要`Clone`：`iter`按值产出键，存放了键的实现（如`HashMap`）需要clone每个键
+	type Key: Clone;
 	type Val;
+    /// 只读迭代器，产出`(key, &value)`；键按值返回而不是`&Key`，因为VecMap的键即位置，并不存放键，无法返回引用
+    type Iter<'a>: Iterator<Item = (Self::Key, &'a Self::Val)> where Self: 'a;
 	fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 	fn with_capacity(capacity: usize) -> Self;
     fn capacity(&self) -> usize;
+    /// 预留至少`additional`个值的空间，不能预留的实现可以忽略
+    fn reserve(&mut self, _additional: usize) {}
     fn mem_size(&self) -> usize;
     fn contains(&self, key: &Self::Key) -> bool;
     fn get(&self, key: &Self::Key) -> Option<&Self::Val>;
     fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Val>;
+    /// # Safety
+    /// 调用者须保证`key`对应的值存在
     unsafe fn get_unchecked(&self, key: &Self::Key) -> &Self::Val;
+    /// # Safety
+    /// 调用者须保证`key`对应的值存在
     unsafe fn get_unchecked_mut(&mut self, key: &Self::Key) -> &mut Self::Val;
+    /// # Safety
+    /// 调用者须保证`key`对应的值存在
     unsafe fn remove_unchecked(&mut self, key: &Self::Key) -> Self::Val;
     fn insert(&mut self, key: Self::Key, val: Self::Val) -> Option<Self::Val>;
     fn remove(&mut self, key: &Self::Key) -> Option<Self::Val>;
+    /// 同时取到两个键对应的可变值，键不存在或两个键相等时，对应位置返回None
+    fn get2_mut(&mut self, a: &Self::Key, b: &Self::Key) -> (Option<&mut Self::Val>, Option<&mut Self::Val>);
+    /// 获取一个只读迭代器
+    fn iter(&self) -> Self::Iter<'_>;
+    /// 获取所有键的迭代器
+    fn keys(&self) -> impl Iterator<Item = Self::Key> {
+        self.iter().map(|(k, _)| k)
+    }
+    /// 获取所有值的迭代器
+    fn values(&self) -> impl Iterator<Item = &Self::Val> {
+        self.iter().map(|(_, v)| v)
+    }
+    /// 取到`key`对应的可变值，如果不存在，用`f`的返回值插入后再返回
+    fn get_or_insert_with(&mut self, key: Self::Key, f: impl FnOnce() -> Self::Val) -> &mut Self::Val {
+        if !self.contains(&key) {
+            self.insert(key.clone(), f());
+        }
+        unsafe { self.get_unchecked_mut(&key) }
+    }
+    /// 插入迭代器中的所有键值对，键冲突时覆盖
+    fn extend(&mut self, iter: impl IntoIterator<Item = (Self::Key, Self::Val)>) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+    /// 清空数据
+    fn clear(&mut self);
+    /// 只保留`f`返回true的值
+    /// 默认实现先收集所有键再逐个判断、移除，实现可以覆盖为更高效的版本
+    fn retain(&mut self, mut f: impl FnMut(&Self::Key, &mut Self::Val) -> bool) {
+        let keys: Vec<Self::Key> = self.keys().collect();
+        for k in keys {
+            if let Some(v) = self.get_mut(&k) {
+                if !f(&k, v) {
+                    self.remove(&k);
+                }
+            }
+        }
+    }
+}
+
+// 测试用的最小Map实现，键值对存放在Vec中，只实现必需的方法，其余使用默认实现
+#[cfg(test)]
+struct ListMap(Vec<(u32, u32)>);
+
+#[cfg(test)]
+impl ListMap {
+    fn position(&self, key: &u32) -> Option<usize> {
+        self.0.iter().position(|(k, _)| k == key)
+    }
+}
+
+#[cfg(test)]
+impl Map for ListMap {
+    type Key = u32;
+    type Val = u32;
+    type Iter<'a> = std::iter::Map<std::slice::Iter<'a, (u32, u32)>, fn(&'a (u32, u32)) -> (u32, &'a u32)>;
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn with_capacity(capacity: usize) -> Self {
+        ListMap(Vec::with_capacity(capacity))
+    }
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+    fn mem_size(&self) -> usize {
+        self.0.capacity() * std::mem::size_of::<(u32, u32)>()
+    }
+    fn contains(&self, key: &u32) -> bool {
+        self.position(key).is_some()
+    }
+    fn get(&self, key: &u32) -> Option<&u32> {
+        self.position(key).map(|i| &self.0[i].1)
+    }
+    fn get_mut(&mut self, key: &u32) -> Option<&mut u32> {
+        self.position(key).map(|i| &mut self.0[i].1)
+    }
+    unsafe fn get_unchecked(&self, key: &u32) -> &u32 {
+        self.get(key).unwrap()
+    }
+    unsafe fn get_unchecked_mut(&mut self, key: &u32) -> &mut u32 {
+        self.get_mut(key).unwrap()
+    }
+    unsafe fn remove_unchecked(&mut self, key: &u32) -> u32 {
+        self.remove(key).unwrap()
+    }
+    fn insert(&mut self, key: u32, val: u32) -> Option<u32> {
+        match self.get_mut(&key) {
+            Some(v) => Some(std::mem::replace(v, val)),
+            None => {
+                self.0.push((key, val));
+                None
+            }
+        }
+    }
+    fn remove(&mut self, key: &u32) -> Option<u32> {
+        self.position(key).map(|i| self.0.swap_remove(i).1)
+    }
+    fn get2_mut(&mut self, a: &u32, b: &u32) -> (Option<&mut u32>, Option<&mut u32>) {
+        if a == b {
+            return (None, None);
+        }
+        let mut r = (None, None);
+        for (k, v) in self.0.iter_mut() {
+            if k == a {
+                r.0 = Some(v);
+            } else if k == b {
+                r.1 = Some(v);
+            }
+        }
+        r
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.0.iter().map(|(k, v)| (*k, v))
+    }
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+#[test]
+fn test_default_retain(){
+    let mut map = ListMap::with_capacity(0);
+    map.extend((1..11).map(|i| (i, i)));
+    map.retain(|k, v| {
+        *v += 1;
+        k % 3 != 0
+    });
+    assert_eq!(map.len(), 7);
+    assert_eq!(map.get(&3), None);
+    assert_eq!(map.get(&4), Some(&5));
+    map.retain(|_, _| false);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_default_reserve(){
+    let mut map = ListMap::with_capacity(0);
+    // 默认实现不预留空间
+    Map::reserve(&mut map, 100);
+    assert_eq!(map.capacity(), 0);
 }
@@ -1,4 +1,4 @@
-//! 实现数据结构`SmallVecMap`, 并为`SmallVecMap`实现了`Map<K=u32,V=T>`
+//! 实现数据结构`SmallVecMap`, 并为`SmallVecMap`实现了`Map<Key=K,Val=T>`，键类型`K`默认为`u32`
 //! 就像其名字描述的一样，`SmallVecMap`以Vec作为数据结构，实现索引到值得映射。
 //! 
 //! SmallVecMap通常用于存放的少量数据，数据的key可以跨度比较大。
@@ -6,379 +6,2540 @@
 //!
 use std::mem::replace;
 use std::fmt::{Debug};
-use std::ops::{Index, IndexMut};
-use std::slice::{Iter, IterMut};
-use smallvec::{SmallVec, Array};
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
+use std::slice;
+use smallvec::{CollectionAllocErr, SmallVec};
 use pi_null::Null;
 
 
 use crate::Map;
 
-pub struct Arr<T, const N: usize>([(T, u32); N]);
+/// SmallVecMap的键类型，需要能与indexs的下标相互转换，`K::null()`不能作为键
+/// 键直接作为indexs的下标，因此过大的键会使indexs占用大量内存
+pub trait MapKey: Copy + Ord + Hash + Debug + Null + Send + Sync {
+    /// 转为indexs的下标
+    fn index(self) -> usize;
+    /// 由indexs的下标转为键
+    fn from_index(index: usize) -> Self;
+}
+
+macro_rules! impl_map_key {
+    ($($t:ty),*) => {
+        $(
+            impl MapKey for $t {
+                #[inline(always)]
+                fn index(self) -> usize {
+                    self as usize
+                }
+                #[inline(always)]
+                fn from_index(index: usize) -> Self {
+                    index as $t
+                }
+            }
+        )*
+    };
+}
 
-unsafe impl<T, const N: usize> Array for Arr<T, N> {
-    type Item = (T, u32);
+impl_map_key!(u8, u16, u32, usize);
 
-    fn size() -> usize {
-        N
+// u64在32位目标（如wasm32）上比usize宽，不能直接截断
+impl MapKey for u64 {
+    #[inline(always)]
+    fn index(self) -> usize {
+        usize::try_from(self).expect("SmallVecMap key exceeds usize")
+    }
+    #[inline(always)]
+    fn from_index(index: usize) -> Self {
+        index as u64
     }
 }
 
 /// 数据结构SmallVecMap
-#[derive(Debug, Hash, Clone)]
-pub struct SmallVecMap<T, const N: usize> {
+/// 值在entries中的位置以u32记录，且`u32::null()`表示空位，因此最多存放`u32::MAX`个值
+/// 键的类型为`K`（默认为`u32`），`K::null()`不能作为键
+/// `N`为内联存放的值个数，超过后溢出到堆上，默认为8
+pub struct SmallVecMap<T, const N: usize = 8, K = u32> {
     indexs: Vec<u32>,// Chunk of memory
-    entries: SmallVec<Arr<T, N>>,// Chunk of memory
+    entries: SmallVec<[(T, K); N]>,// Chunk of memory
+}
+
+/// 内联存放8个值的SmallVecMap
+pub type DefaultSmallVecMap<T> = SmallVecMap<T, 8>;
+
+/// 某个键在SmallVecMap中的状态，见`SmallVecMap::status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// 存在值
+    Occupied,
+    /// 在索引范围内，但没有值
+    Vacant,
+    /// 超出索引范围，插入时索引需要扩容
+    OutOfRange,
+}
+
+/// `try_insert`时，键已存在的错误
+#[derive(Debug)]
+pub struct OccupiedError<'a, T> {
+    /// 已存在的值
+    pub entry: &'a mut T,
+    /// 未能插入的值
+    pub value: T,
+}
+
+impl<T: Debug> std::fmt::Display for OccupiedError<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to insert {:?}, key already exists with value {:?}", self.value, self.entry)
+    }
+}
+
+impl<T: Debug> std::error::Error for OccupiedError<'_, T> {}
+
+/// `get_or_err`、`get_mut_or_err`时，键不存在的错误，包含所查找的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingKey<K = u32>(pub K);
+
+impl<K: Debug> std::fmt::Display for MissingKey<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SmallVecMap key {:?} not found", self.0)
+    }
+}
+
+impl<K: Debug> std::error::Error for MissingKey<K> {}
+
+/// `try_reserve`时，分配失败的错误，区分是indexs还是entries分配失败
+#[derive(Debug)]
+pub enum TryReserveError {
+    /// indexs（`Vec`）分配失败
+    Indexs(std::collections::TryReserveError),
+    /// entries（`SmallVec`）分配失败
+    Entries(CollectionAllocErr),
+}
+
+impl std::fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryReserveError::Indexs(e) => write!(f, "SmallVecMap indexs reserve failed: {}", e),
+            TryReserveError::Entries(e) => write!(f, "SmallVecMap entries reserve failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TryReserveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TryReserveError::Indexs(e) => Some(e),
+            TryReserveError::Entries(_) => None,
+        }
+    }
 }
 
-impl<T, const N: usize> Default for SmallVecMap<T, N> {
+impl<T, const N: usize, K: MapKey> Default for SmallVecMap<T, N, K> {
     fn default() -> Self {
         SmallVecMap::new()
     }
 }
-// impl<T: Clone, const N: usize> Clone for SmallVecMap<T, N> {
-//     fn clone(&self) -> Self {
-//         SmallVecMap {
-//             indexs: self.indexs.clone(),
-//             entries: self.entries.clone(),
-//         }
-//     }
-// }
-impl<T, const N: usize> From<Vec<(T, u32)>> for SmallVecMap<T, N> {
-    fn from(value: Vec<(T, u32)>) -> Self {
-        let len = value.len();
-        let mut indexs = Vec::with_capacity(len);
-        for idx in 0..len {
-            indexs.push(idx as u32);
+impl<T: Clone, const N: usize, K: MapKey> Clone for SmallVecMap<T, N, K> {
+    fn clone(&self) -> Self {
+        SmallVecMap {
+            indexs: self.indexs.clone(),
+            entries: self.entries.clone(),
         }
-        Self {
-            indexs,
-            entries: SmallVec::from_vec(value),
+    }
+    /// 复用self已有的indexs和entries的内存
+    fn clone_from(&mut self, source: &Self) {
+        self.indexs.clone_from(&source.indexs);
+        self.entries.clone_from(&source.entries);
+    }
+}
+/// 注意元素为`(value, key)`，与entries的存储顺序一致；按`(key, value)`构造请使用`from_pairs`
+/// 每个值按其自身的key建立索引（旧版本按位置建立，只在key等于位置时才正确），键重复或为null时panic
+impl<T, const N: usize, K: MapKey> From<Vec<(T, K)>> for SmallVecMap<T, N, K> {
+    fn from(value: Vec<(T, K)>) -> Self {
+        let max_key = value.iter().map(|(_, k)| *k).max();
+        Self::from_vec_with_max_key(value, max_key)
+    }
+}
+/// 与`from_pairs`一样按`(key, value)`顺序插入，键重复时后者覆盖前者
+impl<T, const M: usize, const N: usize, K: MapKey> From<[(K, T); M]> for SmallVecMap<T, N, K> {
+    fn from(value: [(K, T); M]) -> Self {
+        Self::from_pairs(value)
+    }
+}
+/// 像`HashMap`一样输出为`{key: value, ...}`，按键升序排列，不输出内部的indexs和entries
+impl<T: Debug, const N: usize, K: MapKey> Debug for SmallVecMap<T, N, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter_sorted()).finish()
+    }
+}
+/// 按键值映射比较，与entries中的物理顺序无关
+impl<T: PartialEq, const N: usize, K: MapKey> PartialEq for SmallVecMap<T, N, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.entries.iter().all(|(v, k)| other.get(*k) == Some(v))
+    }
+}
+impl<T: Eq, const N: usize, K: MapKey> Eq for SmallVecMap<T, N, K> {}
+/// 按键排序后哈希所有键值对，与`PartialEq`保持一致
+impl<T: Hash, const N: usize, K: MapKey> Hash for SmallVecMap<T, N, K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut pairs: Vec<&(T, K)> = self.entries.iter().collect();
+        pairs.sort_unstable_by_key(|(_, k)| *k);
+        state.write_usize(pairs.len());
+        for (v, k) in pairs {
+            k.hash(state);
+            v.hash(state);
         }
     }
 }
-impl<T, const N: usize> SmallVecMap<T, N> {
+impl<T, const N: usize, K: MapKey> FromIterator<(K, T)> for SmallVecMap<T, N, K> {
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut map = SmallVecMap::with_capacity(iter.size_hint().0);
+        map.extend(iter);
+        map
+    }
+}
+impl<T, const N: usize, K: MapKey> Extend<(K, T)> for SmallVecMap<T, N, K> {
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        self.extend(iter)
+    }
+}
+impl<T, const N: usize, K: MapKey> SmallVecMap<T, N, K> {
     /// 创建一个SmallVecMap实例
     pub fn new() -> Self {
         SmallVecMap::with_capacity(0)
     }
  
     /// 创建一个SmallVecMap实例, 并指定初始化容量
-    pub fn with_capacity(capacity: usize) -> SmallVecMap<T, N> {
+    pub fn with_capacity(capacity: usize) -> SmallVecMap<T, N, K> {
         SmallVecMap {
             indexs: Vec::with_capacity(capacity),
             entries: SmallVec::new(),
         }
     }
 
+    /// 创建一个SmallVecMap实例，为entries预留`entries`个值的容量，并将indexs初始化为可容纳`max_key`的长度
+    /// `max_key`不能为`K::null()`，否则panic
+    pub fn with_capacity_and_max_key(entries: usize, max_key: K) -> SmallVecMap<T, N, K> {
+        assert!(!max_key.is_null(), "SmallVecMap max_key can not be null");
+        SmallVecMap {
+            indexs: vec![u32::null(); max_key.index() + 1],
+            entries: SmallVec::with_capacity(entries),
+        }
+    }
+
+    /// 用`(key, value)`对创建SmallVecMap，键重复时后者覆盖前者
+    /// 注意与`From<Vec<(T, K)>>`的`(value, key)`顺序相反
+    pub fn from_pairs(iter: impl IntoIterator<Item = (K, T)>) -> Self {
+        iter.into_iter().collect()
+    }
+
+    /// 用已按键升序排列（且键不重复）的`Vec<(value, key)>`创建SmallVecMap，省去查找最大键的遍历
+    /// debug下会检查是否有序，release下信任调用者；键重复或为null时panic
+    pub fn from_sorted_by_key(value: Vec<(T, K)>) -> Self {
+        debug_assert!(value.windows(2).all(|w| w[0].1 < w[1].1), "SmallVecMap::from_sorted_by_key, keys are not sorted");
+        let max_key = value.last().map(|(_, k)| *k);
+        Self::from_vec_with_max_key(value, max_key)
+    }
+
+    fn from_vec_with_max_key(value: Vec<(T, K)>, max_key: Option<K>) -> Self {
+        assert!(value.len() <= u32::null() as usize, "SmallVecMap is full, len: {}", value.len());
+        assert!(!max_key.is_some_and(|k| k.is_null()), "SmallVecMap, null key");
+        let mut indexs = vec![u32::null(); max_key.map_or(0, |k| k.index() + 1)];
+        for (i, (_, k)) in value.iter().enumerate() {
+            assert!(!k.is_null(), "SmallVecMap, null key");
+            assert!(indexs[k.index()].is_null(), "SmallVecMap, duplicate key: {:?}", k);
+            indexs[k.index()] = i as u32;
+        }
+        Self {
+            indexs,
+            entries: SmallVec::from_vec(value),
+        }
+    }
+
     /// 获取SmallVecMap当前的容量
     pub fn capacity(&self) -> usize {
         self.indexs.capacity()
     }
 
+    /// 获取entries（存放值）的容量，未溢出到堆上时为`N`
+    pub fn entries_capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// entries是否已溢出到堆上，即曾经存放超过`N`个值且尚未收缩回内联存储
+    pub fn is_spilled(&self) -> bool {
+        self.entries.spilled()
+    }
+
+    /// 获取indexs（键到位置的索引）的容量，同`capacity`
+    pub fn index_capacity(&self) -> usize {
+        self.indexs.capacity()
+    }
+
     /// 扩充容量
     pub fn reserve(&mut self, additional: usize) {
         self.indexs.reserve(additional);
     }
 
+    /// 尝试为indexs和entries各扩充`additional`个容量，分配失败时返回错误而不是终止进程
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.indexs.try_reserve(additional).map_err(TryReserveError::Indexs)?;
+        self.entries.try_reserve(additional).map_err(TryReserveError::Entries)
+    }
+
     /// 扩充容量
     pub fn reserve_exact(&mut self, additional: usize) {
         self.indexs.reserve_exact(additional);
     }
     
+    /// 去掉indexs尾部的空位，并释放多余的容量
+    pub fn shrink_to_fit(&mut self) {
+        let len = self.indexs.iter().rposition(|i| !i.is_null()).map_or(0, |i| i + 1);
+        self.indexs.truncate(len);
+        self.indexs.shrink_to_fit();
+        self.entries.shrink_to_fit();
+    }
+
+    /// 将entries按键排序并重建索引，键值映射不变，按键顺序访问时更连续
+    pub fn compact(&mut self) {
+        self.entries.sort_unstable_by_key(|(_, k)| *k);
+        for (i, (_, k)) in self.entries.iter().enumerate() {
+            self.indexs[k.index()] = i as u32;
+        }
+    }
+
+    /// 克隆出一个紧凑的副本：entries按键排序，indexs去掉尾部空位，容量恰好够用，不修改self
+    pub fn clone_compact(&self) -> Self where T: Clone {
+        let len = self.indexs.iter().rposition(|i| !i.is_null()).map_or(0, |i| i + 1);
+        let mut indexs = vec![u32::null(); len];
+        let mut entries = SmallVec::with_capacity(self.len());
+        for (k, v) in self.iter_sorted() {
+            indexs[k.index()] = entries.len() as u32;
+            entries.push((v.clone(), k));
+        }
+        Self { indexs, entries }
+    }
+
+    /// 克隆为内联存放`M`个值的SmallVecMap，用于调整`N`；entries顺序不变，indexs直接复制
+    pub fn clone_with_capacity<const M: usize>(&self) -> SmallVecMap<T, M, K> where T: Clone {
+        SmallVecMap {
+            indexs: self.indexs.clone(),
+            entries: self.entries.iter().cloned().collect(),
+        }
+    }
+
     /// 清空数据
     pub fn clear(&mut self) {
         self.indexs.clear();
         self.entries.clear();
     }
 
+    /// 移出所有键值对，迭代器被释放后（无论是否迭代完）SmallVecMap为空
+    pub fn drain(&mut self) -> Drain<'_, T, N, K> {
+        self.indexs.clear();
+        Drain(self.entries.drain(..))
+    }
+
     /// 片段当前是否为空
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
-    /// 获取一个只读迭代器，可以获取值所对应的index
-    pub fn iter(&self) -> Iter<'_, (T, u32)> {
-        self.entries.iter()
+    /// 获取一个只读迭代器，产出`(key, &value)`，按entries中的物理顺序排列
+    pub fn iter(&self) -> Iter<'_, T, K> {
+        Iter(self.entries.iter())
+    }
+    /// 获取一个可写迭代器，只产出`&mut value`，键不可修改，需要同时读取键时请使用`iter_mut_keyed`
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(v, _)| v)
+    }
+    /// 获取一个可写迭代器，产出`(key, &mut value)`，键按值返回，不可修改
+    pub fn iter_mut_keyed(&mut self) -> IterMut<'_, T, K> {
+        IterMut(self.entries.iter_mut())
+    }
+    /// 按键从小到大迭代，产出`(key, &value)`，复杂度为O(最大键)
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (K, &T)> {
+        self.iter_sorted_in(0, self.indexs.len())
+    }
+    /// 按键从小到大迭代所有值，顺序只取决于键，与插入、删除的历史无关
+    /// 与`iter_sorted`一样通过扫描indexs实现，无需排序，复杂度为O(最大键)
+    pub fn values_sorted(&self) -> impl Iterator<Item = &T> {
+        self.iter_sorted().map(|(_, v)| v)
+    }
+    /// 按键从小到大迭代键大于等于`start`的值，产出`(key, &value)`，从indexs的`start`位置开始扫描
+    pub fn iter_from(&self, start: K) -> impl Iterator<Item = (K, &T)> {
+        self.iter_sorted_in(start.index(), self.indexs.len())
+    }
+    /// 按键从小到大迭代键在范围`r`中的值，产出`(key, &value)`
+    pub fn range(&self, r: impl RangeBounds<K>) -> impl Iterator<Item = (K, &T)> {
+        let start = match r.start_bound() {
+            Bound::Included(s) => s.index(),
+            Bound::Excluded(s) => s.index().saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match r.end_bound() {
+            Bound::Included(e) => e.index().saturating_add(1),
+            Bound::Excluded(e) => e.index(),
+            Bound::Unbounded => self.indexs.len(),
+        };
+        self.iter_sorted_in(start, end)
+    }
+    // 按键从小到大迭代键在`start..end`中的值
+    fn iter_sorted_in(&self, start: usize, end: usize) -> impl Iterator<Item = (K, &T)> {
+        let end = end.min(self.indexs.len());
+        let start = start.min(end);
+        self.indexs[start..end].iter().enumerate()
+            .filter(|(_, i)| !i.is_null())
+            .map(move |(k, i)| (K::from_index(start + k), &self.entries[*i as usize].0))
     }
-    /// 获取一个可写迭代器，可以获取值所对应的index
-    pub fn iter_mut(&mut self) -> IterMut<'_, (T, u32)> {
-        self.entries.iter_mut()
+    /// 对每个值调用`f(key, &mut value)`，key为值所对应的键，按entries中的物理顺序遍历
+    pub fn apply_all(&mut self, mut f: impl FnMut(K, &mut T)) {
+        for (v, k) in self.entries.iter_mut() {
+            f(*k, v);
+        }
+    }
+    /// 以`init`为初值，对每个键值对调用`f(acc, key, &value)`累积结果，遍历顺序为entries中的物理顺序
+    pub fn fold<B>(&self, init: B, mut f: impl FnMut(B, K, &T) -> B) -> B {
+        self.entries.iter().fold(init, |acc, (v, k)| f(acc, *k, v))
+    }
+    /// 以切片形式取到所有`(value, key)`，按entries中的物理顺序排列，并非按键排序
+    #[inline]
+    pub fn entries_slice(&self) -> &[(T, K)] {
+        self.entries.as_slice()
+    }
+    /// 获取一个只读迭代器，产出`(entries中的物理位置, key, &value)`，用于对照indexs排查索引问题，或配合`remove_at_slot`使用
+    pub fn iter_slots(&self) -> impl Iterator<Item = (usize, K, &T)> {
+        self.entries.iter().enumerate().map(|(i, (v, k))| (i, *k, v))
+    }
+    /// 获取所有键的迭代器
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.entries.iter().map(|(_, k)| *k)
+    }
+    /// 获取所有值的只读迭代器
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(v, _)| v)
+    }
+    /// 获取所有值的可写迭代器
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.entries.iter_mut().map(|(v, _)| v)
     }
     
     /// 替换指定位置的值, 并返回旧值。你应该确认，旧值一定存在，否则将会panic
-    pub unsafe fn replace(&mut self, index: u32, val: T) -> T {
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
+    pub unsafe fn replace(&mut self, index: K, val: T) -> T {
         replace(self.get_unchecked_mut(index), val)
     }
+    /// 替换指定位置的值，存在旧值时返回`Ok(旧值)`，否则不插入，返回`Err(val)`
+    pub fn try_replace(&mut self, index: K, val: T) -> Result<T, T> {
+        match self.get_mut(index) {
+            Some(v) => Ok(replace(v, val)),
+            None => Err(val),
+        }
+    }
     /// 取到某个偏移位置的只读值
-    pub fn get(&self, index: u32) -> Option<&T> {
-        if index as usize >= self.indexs.len() {
+    pub fn get(&self, index: K) -> Option<&T> {
+        if index.index() >= self.indexs.len() {
             return None;
         }
-        let i = self.indexs[index as usize];
+        let i = self.indexs[index.index()];
         if i.is_null() {
             return None;
         }
         Some(&self.entries[i as usize].0)
     }
 
+    /// 取到某个偏移位置的键值对，键取自entries中保存的键
+    pub fn get_key_value(&self, index: K) -> Option<(K, &T)> {
+        let i = *self.indexs.get(index.index())?;
+        if i.is_null() {
+            return None;
+        }
+        let (v, k) = &self.entries[i as usize];
+        Some((*k, v))
+    }
+
     /// 取到某个偏移位置的可变值
-    pub fn get_mut(&mut self, index: u32) -> Option<&mut T> {
-        if index as usize >= self.indexs.len(){
+    pub fn get_mut(&mut self, index: K) -> Option<&mut T> {
+        if index.index() >= self.indexs.len(){
             return None;
         }
-        let i = self.indexs[index as usize];
+        let i = self.indexs[index.index()];
         if i.is_null() {
             return None;
         }
         Some(&mut self.entries[i as usize].0)
     }
 
-    /// 取到某个偏移位置的只读值
-    /// 如果该位置不存在值，将panic
-    pub unsafe fn get_unchecked(&self, index: u32) -> &T {
-        &self.entries[self.indexs[index as usize] as usize].0
+    /// 同`get`，不存在值时返回包含该键的`MissingKey`错误，便于用`?`传递
+    pub fn get_or_err(&self, index: K) -> Result<&T, MissingKey<K>> {
+        self.get(index).ok_or(MissingKey(index))
     }
 
-    /// 取到某个偏移位置的可变值
-    /// 如果该位置不存在值，将panic
-    pub unsafe fn get_unchecked_mut(&mut self, index: u32) -> &mut T {
-        &mut self.entries[self.indexs[index as usize] as usize].0
+    /// 同`get_mut`，不存在值时返回包含该键的`MissingKey`错误，便于用`?`传递
+    pub fn get_mut_or_err(&mut self, index: K) -> Result<&mut T, MissingKey<K>> {
+        self.get_mut(index).ok_or(MissingKey(index))
     }
 
-    /// 在指定位置插入一个值，并返回旧值，如果不存在旧值，返回None
-    pub fn insert(&mut self, index:u32, val: T) -> Option<T>{
-		let len = self.indexs.len();
-        if index as usize > self.indexs.capacity() {
-            self.indexs.reserve(index as usize - self.indexs.capacity());
-            self.indexs.extend((0..index as usize - len + 1).map(|_| u32::null()));
-            self.indexs[index as usize] = self.entries.len() as u32;
-			self.entries.push((val, index));
-            None
-		} else if index as usize > len {
-            self.indexs.extend((0..index as usize - len + 1).map(|_| u32::null()));
-            self.indexs[index as usize] = self.entries.len() as u32;
-			self.entries.push((val, index));
-            None
-		} else if index as usize == len {
-			self.indexs.push(self.entries.len() as u32);
-			self.entries.push((val, index));
-            None
-        } else {
-            let i = unsafe {self.indexs.get_unchecked_mut(index as usize)};
-            if (*i).is_null() {
-                *i = self.entries.len() as u32;
-                self.entries.push((val, index));
-                return None;
+    /// 同时取到多个位置的可变值，任意一个位置不存在值或有重复位置时，返回None
+    pub fn get_disjoint_mut<const M: usize>(&mut self, keys: [K; M]) -> Option<[&mut T; M]> {
+        let mut slots = [0usize; M];
+        for (slot, key) in slots.iter_mut().zip(keys) {
+            match self.indexs.get(key.index()) {
+                Some(i) if !i.is_null() => *slot = *i as usize,
+                _ => return None,
             }
-            Some(replace(&mut self.entries[*i as usize].0, val))
         }
+        // 键与entries位置一一对应，由切片检查位置是否重复
+        self.entries.get_disjoint_mut(slots).ok().map(|r| r.map(|e| &mut e.0))
     }
 
-    /// 移除指定位置的值，返回被移除的值，如果该位置不存在一个值，返回None
-    pub fn remove(&mut self, index: u32) -> Option<T> {
-        if index as usize >= self.indexs.len() {
-            return None;
+    /// 同时取到多个位置的可变值，不检查位置是否存在值、是否重复
+    ///
+    /// # Safety
+    /// 调用者须保证`keys`中每个位置都存在值，且`keys`两两不同，否则为未定义行为
+    pub unsafe fn get_many_unchecked_mut<const M: usize>(&mut self, keys: [K; M]) -> [&mut T; M] {
+        let slots = keys.map(|key| *self.indexs.get_unchecked(key.index()) as usize);
+        self.entries.get_disjoint_unchecked_mut(slots).map(|e| &mut e.0)
+    }
+
+    /// 同时取到两个位置的可变值，位置不存在值或两个位置相等时，对应位置返回None
+    pub fn get2_mut(&mut self, a: K, b: K) -> (Option<&mut T>, Option<&mut T>) {
+        if a == b {
+            return (None, None);
         }
-        let i = unsafe {self.indexs.get_unchecked_mut(index as usize)};
-        if (*i).is_null() {
-            return None
+        match (self.contains(a), self.contains(b)) {
+            (true, true) => {
+                let [a, b] = self.get_disjoint_mut([a, b]).unwrap();
+                (Some(a), Some(b))
+            }
+            (true, false) => (self.get_mut(a), None),
+            (false, true) => (None, self.get_mut(b)),
+            (false, false) => (None, None),
+        }
+    }
+
+    /// 交换两个位置上的值，任意一个位置不存在值时不做任何操作，返回false
+    pub fn swap(&mut self, a: K, b: K) -> bool {
+        if a == b {
+            return self.contains(a);
         }
-        if *i as usize + 1 == self.entries.len() {
-            *i = u32::null();
-            return Some(self.entries.pop().unwrap().0)
+        match self.get_disjoint_mut([a, b]) {
+            Some([a, b]) => {
+                std::mem::swap(a, b);
+                true
+            }
+            None => false,
         }
-        let i = replace(i, u32::null()) as usize;
-        // 从尾部交换元素到指定位置
-        let r = Some(self.entries.swap_remove(i).0);
-        // 修复索引
-        self.indexs[self.entries[i].1 as usize] = i as u32;
-        r
     }
 
-    /// 移除指定位置的值，返回被移除的值，如果该位置不存在一个值将panic
-    pub unsafe fn remove_unchecked(&mut self, index: u32) -> T {
-        let i = &mut self.indexs[index as usize];
-        if *i as usize + 1 == self.entries.len() {
-            *i = u32::null();
-            return self.entries.pop().unwrap().0
+    /// 取到某个偏移位置的只读值
+    /// 如果该位置不存在值，将panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
+    pub unsafe fn get_unchecked(&self, index: K) -> &T {
+        &self.entries[self.indexs[index.index()] as usize].0
+    }
+
+    /// 取到某个偏移位置的可变值
+    /// 如果该位置不存在值，将panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
+    pub unsafe fn get_unchecked_mut(&mut self, index: K) -> &mut T {
+        &mut self.entries[self.indexs[index.index()] as usize].0
+    }
+
+    /// 在指定位置插入一个值，并返回旧值，如果不存在旧值，返回None
+    /// `K::null()`(对于`u32`即`u32::MAX`)不能作为键，否则将panic
+    pub fn insert(&mut self, index: K, val: T) -> Option<T>{
+        assert!(!index.is_null(), "SmallVecMap key can not be null");
+        if index.index() >= self.indexs.len() {
+            self.indexs.resize(index.index() + 1, u32::null());
         }
-        let i = replace(i, u32::null()) as usize;
-        // 从尾部交换元素到指定位置
-        let r = self.entries.swap_remove(i).0;
-        // 修复索引
-        self.indexs[self.entries[i].1 as usize] = i as u32;
-        r
+        let i = unsafe {self.indexs.get_unchecked_mut(index.index())};
+        if (*i).is_null() {
+            // entries的位置用u32记录，u32::null()表示空位，不能被用作位置
+            assert!(self.entries.len() < u32::null() as usize, "SmallVecMap is full, len: {}", self.entries.len());
+            *i = self.entries.len() as u32;
+            self.entries.push((val, index));
+            return None;
+        }
+        Some(replace(&mut self.entries[*i as usize].0, val))
     }
 
-    /// 判断指定位置是否存在一个值
-    pub fn contains(&self, index: u32) -> bool {
-        if index as usize >= self.indexs.len(){
+    /// 在指定位置插入值是否会使indexs或entries重新分配内存，不修改任何数据
+    /// 已存在值时`insert`只替换该值，返回false
+    pub fn insert_would_grow(&self, index: K) -> bool {
+        if self.contains(index) {
             return false;
         }
-        return !self.indexs[index as usize].is_null()
+        index.index() >= self.indexs.capacity() || self.entries.len() == self.entries.capacity()
     }
 
-    /// 取到SmallVecMap的长度
-    #[inline]
-    pub fn len(&self) -> usize {
-        self.entries.len()
+    /// 同`insert`，并额外返回本次插入是否导致indexs或entries重新分配了内存
+    pub fn insert_tracked(&mut self, index: K, val: T) -> (Option<T>, bool) {
+        let caps = (self.indexs.capacity(), self.entries.capacity());
+        let r = self.insert(index, val);
+        (r, caps != (self.indexs.capacity(), self.entries.capacity()))
     }
-}
 
+    /// 在指定位置插入一个值，并返回新值的可变引用；如果已存在值，不覆盖，返回`OccupiedError`
+    pub fn try_insert(&mut self, index: K, val: T) -> Result<&mut T, OccupiedError<'_, T>> {
+        match self.indexs.get(index.index()) {
+            Some(i) if !i.is_null() => Err(OccupiedError {
+                entry: &mut self.entries[*i as usize].0,
+                value: val,
+            }),
+            _ => {
+                self.insert(index, val);
+                Ok(&mut self.entries.last_mut().unwrap().0)
+            }
+        }
+    }
 
-/// 为SmallVecMap实现Map
-impl<T, const N: usize> Map for SmallVecMap<T, N> {
-	type Key = u32;
-	type Val = T;
-    #[inline]
-    fn get(&self, key: &Self::Key) -> Option<&T> {
-        self.get(*key)
+    /// 取到指定位置的可变值，如果不存在，用`f`的返回值插入后再返回
+    pub fn get_or_insert_with(&mut self, index: K, f: impl FnOnce() -> T) -> &mut T {
+        let i = match self.indexs.get(index.index()) {
+            Some(i) if !i.is_null() => *i as usize,
+            _ => {
+                // 新值总是被追加到entries尾部
+                self.insert(index, f());
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[i].0
     }
 
-    #[inline]
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut T> {
-        self.get_mut(*key)
+    /// 取到指定位置的可变值，如果不存在，插入`T::default()`后再返回
+    pub fn get_or_default(&mut self, index: K) -> &mut T where T: Default {
+        self.get_or_insert_with(index, T::default)
     }
 
-    #[inline]
-    unsafe fn get_unchecked(&self, key: &Self::Key) -> &T {
-        self.get_unchecked(*key)
+    /// 不存在值时插入`default`并返回true，否则对已有值调用`modify`并返回false，只查找一次位置
+    pub fn insert_or_modify(&mut self, index: K, default: T, modify: impl FnOnce(&mut T)) -> bool {
+        match self.indexs.get(index.index()) {
+            Some(i) if !i.is_null() => {
+                modify(&mut self.entries[*i as usize].0);
+                false
+            }
+            _ => {
+                self.insert(index, default);
+                true
+            }
+        }
     }
 
-    #[inline]
-    unsafe fn get_unchecked_mut(&mut self, key: &Self::Key) -> &mut T {
-        self.get_unchecked_mut(*key)
+    /// 对每个键值对调用`f`：返回None则移除，返回`Some((新键, 新值))`则以新键保存
+    /// 完成后重建索引；多个值被映射到同一个键时，后处理的值覆盖先处理的值
+    pub fn retain_map(&mut self, mut f: impl FnMut(K, T) -> Option<(K, T)>) {
+        let old = std::mem::take(&mut self.entries);
+        self.indexs.clear();
+        self.entries.reserve(old.len());
+        for (v, k) in old {
+            if let Some((k, v)) = f(k, v) {
+                self.insert(k, v);
+            }
+        }
     }
 
-    #[inline]
-    unsafe fn remove_unchecked(&mut self, key: &Self::Key) -> T {
-        self.remove_unchecked(*key)
+    /// 移除所有键大于`max_key`的值，并将indexs截断到`max_key + 1`
+    pub fn truncate_keys(&mut self, max_key: K) {
+        self.retain(|k, _| k <= max_key);
+        self.indexs.truncate(max_key.index().saturating_add(1));
     }
 
-    #[inline]
-    fn insert(&mut self, key: Self::Key, val: T) -> Option<T> {
-        self.insert(key, val)
+    /// 插入迭代器中的所有键值对，键冲突时覆盖
+    /// 同时实现了`Extend`和`Map::extend`，作为固有方法可避免两者同时在作用域中时调用产生歧义
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = (K, T)>) {
+        let iter = iter.into_iter();
+        self.entries.reserve(iter.size_hint().0);
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
     }
 
-    #[inline]
-    fn remove(&mut self, key: &Self::Key) -> Option<T> {
-        self.remove(*key)
+    /// 追加键严格升序且都大于当前最大键的键值对，每个值直接追加到entries尾部，省去`insert`的查找和覆盖判断
+    /// 先收集并检查所有键，再将indexs一次扩容到最后一个键，entries也只预留一次
+    /// 键不满足要求时panic，此时self不被修改
+    pub fn extend_ascending(&mut self, iter: impl IntoIterator<Item = (K, T)>) {
+        let pairs: Vec<(K, T)> = iter.into_iter().collect();
+        let mut last = self.max_key();
+        for (k, _) in &pairs {
+            assert!(last.is_none_or(|last| *k > last), "SmallVecMap::extend_ascending, key {:?} is not greater than {:?}", k, last);
+            assert!(!k.is_null(), "SmallVecMap key can not be null");
+            last = Some(*k);
+        }
+        let Some(&(last, _)) = pairs.last() else { return };
+        assert!(self.entries.len() + pairs.len() <= u32::null() as usize, "SmallVecMap is full, len: {}", self.entries.len());
+        // 键都大于已有的键，indexs只需在尾部增长；indexs可能已带有足够的尾部空位
+        if last.index() >= self.indexs.len() {
+            self.indexs.resize(last.index() + 1, u32::null());
+        }
+        self.entries.reserve(pairs.len());
+        for (k, v) in pairs {
+            self.indexs[k.index()] = self.entries.len() as u32;
+            self.entries.push((v, k));
+        }
     }
 
-    #[inline]
-    fn contains(&self, key: &Self::Key) -> bool {
-        self.contains(*key)
+    /// 将`other`中的所有值移入self，键冲突时用`other`中的值覆盖，完成后`other`为空
+    pub fn append(&mut self, other: &mut SmallVecMap<T, N, K>) {
+        // 按other中实际的最大键预先扩容indexs，other的indexs可能带有尾部空位
+        if let Some(max) = other.max_key() {
+            if max.index() >= self.indexs.len() {
+                self.indexs.resize(max.index() + 1, u32::null());
+            }
+        }
+        self.extend(other.drain());
     }
 
-    #[inline]
-    fn len(&self) -> usize {
-        self.len()
+    /// 将`other`合并到self，键冲突时调用`resolve(已有值, 新值)`合并，否则直接插入
+    pub fn merge_with(&mut self, other: SmallVecMap<T, N, K>, mut resolve: impl FnMut(&mut T, T)) {
+        // 按other中实际的最大键预先扩容indexs，other的indexs可能带有尾部空位
+        if let Some(max) = other.max_key() {
+            if max.index() >= self.indexs.len() {
+                self.indexs.resize(max.index() + 1, u32::null());
+            }
+        }
+        for (k, v) in other {
+            match self.get_mut(k) {
+                Some(e) => resolve(e, v),
+                None => {
+                    self.insert(k, v);
+                }
+            }
+        }
     }
-    #[inline]
-    fn capacity(&self) -> usize {
-        self.indexs.capacity()
+
+    /// 将键大于等于`at`的值移出到一个新的SmallVecMap中返回
+    pub fn split_off(&mut self, at: K) -> SmallVecMap<T, N, K> {
+        let mut other = SmallVecMap::new();
+        let mut i = 0;
+        while i < self.entries.len() {
+            if self.entries[i].1 < at {
+                i += 1;
+                continue;
+            }
+            let (v, k) = self.entries.swap_remove(i);
+            if i < self.entries.len() {
+                self.indexs[self.entries[i].1.index()] = i as u32;
+            }
+            other.insert(k, v);
+        }
+        // 键大于等于at的位置都已被移出，indexs截到剩余的最大键为止，不留尾部空位
+        self.indexs.truncate(at.index());
+        self.indexs.truncate(self.max_key().map_or(0, |k| k.index() + 1));
+        other
     }
-    #[inline]
-    fn mem_size(&self) -> usize {
-        self.capacity() * std::mem::size_of::<T>()
-	}
-	
-	fn with_capacity(capacity: usize) -> Self {
-		SmallVecMap::with_capacity(capacity)
-	}
-}
 
-impl<T, const N: usize> Index<usize> for SmallVecMap<T, N> {
-    type Output = T;
+    /// 只保留`f`返回true的值，与`remove`一样会去掉indexs尾部的空位
+    pub fn retain<F: FnMut(K, &mut T) -> bool>(&mut self, mut f: F) {
+        let mut max_removed = None;
+        let mut i = 0;
+        while i < self.entries.len() {
+            let k = self.entries[i].1;
+            if f(k, &mut self.entries[i].0) {
+                i += 1;
+                continue;
+            }
+            self.indexs[k.index()] = u32::null();
+            max_removed = max_removed.max(Some(k));
+            // 从尾部交换元素到当前位置，下一轮继续检查该位置
+            self.entries.swap_remove(i);
+            if i < self.entries.len() {
+                self.indexs[self.entries[i].1.index()] = i as u32;
+            }
+        }
+        if let Some(k) = max_removed {
+            self.trim_tail(k);
+        }
+    }
 
-    fn index(&self, index: usize) -> &T {
-        unsafe { self.get_unchecked(index as u32) }
+    /// 移除并产出`f`返回true的键值对，其余值保留；迭代器被释放时会处理完剩余的值
+    /// 与`remove`一样会去掉indexs尾部的空位
+    pub fn drain_filter<F: FnMut(K, &mut T) -> bool>(&mut self, f: F) -> DrainFilter<'_, T, N, F, K> {
+        DrainFilter { map: self, cur: 0, pred: f }
     }
-}
 
-impl<T, const N: usize> IndexMut<usize> for SmallVecMap<T, N> {
-    fn index_mut(&mut self, index: usize) -> &mut T {
-        unsafe { self.get_unchecked_mut(index as u32) }
+    /// 批量移除`keys`中的值，返回实际移除的个数，不存在的键和重复的键被忽略
+    /// 按entries位置从高到低移除，每次交换到空位的尾部元素都不在待移除之列，只需修复一次索引
+    /// 与`remove`一样会去掉indexs尾部的空位
+    pub fn remove_many(&mut self, keys: &[K]) -> usize {
+        let mut slots = Vec::with_capacity(keys.len());
+        let mut max_removed = None;
+        for &k in keys {
+            if let Some(i) = self.indexs.get_mut(k.index()) {
+                if !i.is_null() {
+                    slots.push(replace(i, u32::null()) as usize);
+                    max_removed = max_removed.max(Some(k));
+                }
+            }
+        }
+        slots.sort_unstable_by(|a, b| b.cmp(a));
+        for &i in &slots {
+            self.entries.swap_remove(i);
+            if i < self.entries.len() {
+                self.indexs[self.entries[i].1.index()] = i as u32;
+            }
+        }
+        if let Some(k) = max_removed {
+            self.trim_tail(k);
+        }
+        slots.len()
     }
-}
 
+    /// 移除并返回entries尾部的键值对，为空时返回None；与`remove`一样会去掉indexs尾部的空位
+    pub fn pop(&mut self) -> Option<(K, T)> {
+        let (v, k) = self.entries.pop()?;
+        self.indexs[k.index()] = u32::null();
+        self.trim_tail(k);
+        Some((k, v))
+    }
 
-#[cfg(test)]
-use std::time::Instant;
-#[test]
-fn test_time(){
-    let mut map: SmallVecMap<u32, 16> = SmallVecMap::new();
-    let _cc = map.clone();
-    let mut arr = Vec::with_capacity(100000);
-    let time = Instant::now();
-    for i in 0..10000 {
-        arr.push(i as f32 + 0.5);
+    /// 移除指定位置的值，返回被移除的值，如果该位置不存在一个值，返回None
+    pub fn remove(&mut self, index: K) -> Option<T> {
+        self.remove_entry(index).map(|(_, v)| v)
     }
-    println!("insert vec time: {:?}", Instant::now() - time);
 
-    let time = Instant::now();
-    for i in 1..10001 {
-        map.insert(i, i);
+    /// 移除指定位置的值，返回被移除的键值对，如果该位置不存在一个值，返回None
+    /// 移除的是indexs的最后一位时，会一并去掉indexs尾部的空位（不释放容量，释放容量请调用`shrink_to_fit`）
+    pub fn remove_entry(&mut self, index: K) -> Option<(K, T)> {
+        let i = *self.indexs.get(index.index())?;
+        if i.is_null() {
+            return None
+        }
+        Some(unsafe { self.remove_at_slot(i as usize) })
     }
-    println!("insert SmallVecMap time: {:?}", Instant::now() - time);
 
+    /// 移除指定位置的值，返回被移除的值，如果该位置不存在一个值将panic
+    /// 与`remove`一样会去掉indexs尾部的空位
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
+    pub unsafe fn remove_unchecked(&mut self, index: K) -> T {
+        let i = self.indexs[index.index()] as usize;
+        self.remove_at_slot(i).1
+    }
 
-    let mut map: SmallVecMap<f32, 16> = SmallVecMap::new();
+    /// 移除entries中物理位置`slot`上的值，修复索引，返回被移除的键值对
+    /// 配合`entries_slice`、`iter_slots`使用，已知物理位置时省去按键查找
+    ///
+    /// # Safety
+    /// 调用者须保证`slot < len()`
+    pub unsafe fn remove_at_slot(&mut self, slot: usize) -> (K, T) {
+        // 从尾部交换元素到指定位置
+        let (v, k) = self.entries.swap_remove(slot);
+        self.indexs[k.index()] = u32::null();
+        if slot < self.entries.len() {
+            // 修复索引
+            self.indexs[self.entries[slot].1.index()] = slot as u32;
+        }
+        self.trim_tail(k);
+        (k, v)
+    }
 
-    let time = Instant::now();
-    for i in 1..10001 {
-        map.insert(i, 1.0);
+    // 键`index`被移除后，如果它是indexs的最后一位，去掉indexs尾部的空位
+    fn trim_tail(&mut self, index: K) {
+        if index.index() + 1 == self.indexs.len() {
+            let len = self.indexs.iter().rposition(|i| !i.is_null()).map_or(0, |i| i + 1);
+            self.indexs.truncate(len);
+        }
     }
-    println!("insert SmallVecMap time: {:?}", Instant::now() - time);
 
+    /// 判断指定位置是否存在一个值
+    pub fn contains(&self, index: K) -> bool {
+        if index.index() >= self.indexs.len(){
+            return false;
+        }
+        !self.indexs[index.index()].is_null()
+    }
+
+    /// 是否包含`keys`中的所有键，遇到第一个不存在的键即返回false；`keys`为空时返回true
+    pub fn contains_all(&self, keys: &[K]) -> bool {
+        keys.iter().all(|k| self.contains(*k))
+    }
+
+    /// 是否包含`keys`中的任意一个键，遇到第一个存在的键即返回true；`keys`为空时返回false
+    pub fn contains_any(&self, keys: &[K]) -> bool {
+        keys.iter().any(|k| self.contains(*k))
+    }
+
+    /// 查看指定位置的状态，不插入值
+    pub fn status(&self, index: K) -> Slot {
+        match self.indexs.get(index.index()) {
+            None => Slot::OutOfRange,
+            Some(i) if i.is_null() => Slot::Vacant,
+            Some(_) => Slot::Occupied,
+        }
+    }
+
+    /// 取到键最小的键值对，为空时返回None
+    pub fn first_key_value(&self) -> Option<(K, &T)> {
+        self.entries.iter().min_by_key(|(_, k)| *k).map(|(v, k)| (*k, v))
+    }
+
+    /// 取到键最大的键值对，为空时返回None
+    pub fn last_key_value(&self) -> Option<(K, &T)> {
+        self.entries.iter().max_by_key(|(_, k)| *k).map(|(v, k)| (*k, v))
+    }
+
+    /// 取到当前最大的键，为空时返回None；从indexs尾部查找第一个非空位，复杂度为O(尾部空位数)
+    pub fn max_key(&self) -> Option<K> {
+        self.indexs.iter().rposition(|i| !i.is_null()).map(K::from_index)
+    }
+
+    /// 键的密度，即`len() / (最大键 + 1)`，为空时返回0.0
+    pub fn density(&self) -> f32 {
+        match self.last_key_value() {
+            Some((max, _)) => self.len() as f32 / (max.index() as f32 + 1.0),
+            None => 0.0,
+        }
+    }
+
+    /// 键是否恰好为`0..len()`，为空时返回true
+    pub fn is_contiguous(&self) -> bool {
+        // 键不重复，因此最大键为len() - 1时，键恰好为0..len()
+        match self.last_key_value() {
+            Some((max, _)) => max.index() + 1 == self.len(),
+            None => true,
+        }
+    }
+
+    /// indexs的碎片情况，返回`(空位数, 总位数)`
+    /// 每个值恰好占用indexs中的一个位置，因此只需读取两个长度，复杂度为O(1)
+    pub fn fragmentation(&self) -> (usize, usize) {
+        (self.indexs.len() - self.entries.len(), self.indexs.len())
+    }
+
+    /// indexs中空位的比例是否超过`threshold`（0.0到1.0），可据此决定是否调用`compact`、`shrink_to_fit`
+    /// 与`fragmentation`一样只读取两个长度，indexs为空时返回false
+    pub fn should_compact(&self, threshold: f32) -> bool {
+        let (free, total) = self.fragmentation();
+        total > 0 && free as f32 > total as f32 * threshold
+    }
+
+    /// 判断是否存在与`v`相等的值，复杂度为O(n)
+    pub fn contains_value(&self, v: &T) -> bool where T: PartialEq {
+        self.entries.iter().any(|(val, _)| val == v)
+    }
+
+    /// 查找第一个满足`pred`的值所对应的键，复杂度为O(n)
+    pub fn find_key(&self, pred: impl Fn(&T) -> bool) -> Option<K> {
+        self.entries.iter().find(|(v, _)| pred(v)).map(|(_, k)| *k)
+    }
+
+    /// 转为`Vec<(value, key)>`，是`From<Vec<(T, K)>>`的逆操作
+    /// 结果按entries中的物理顺序排列，并非按键排序；对`From<Vec>`构建且未修改过的SmallVecMap，顺序与原Vec一致
+    pub fn into_vec(self) -> Vec<(T, K)> {
+        self.entries.into_vec()
+    }
+
+    /// 用`f`转换每个值，键及其对应关系不变；entries中的顺序不变，因此直接复用indexs
+    pub fn map_values<U>(self, mut f: impl FnMut(T) -> U) -> SmallVecMap<U, N, K> {
+        SmallVecMap {
+            indexs: self.indexs,
+            entries: self.entries.into_iter().map(|(v, k)| (f(v), k)).collect(),
+        }
+    }
+
+    /// 同`map_values`，`f`返回错误时立即停止，返回出错值对应的键和错误
+    pub fn try_map_values<U, E>(self, mut f: impl FnMut(T) -> Result<U, E>) -> Result<SmallVecMap<U, N, K>, (K, E)> {
+        let mut entries = SmallVec::with_capacity(self.entries.len());
+        for (v, k) in self.entries {
+            entries.push((f(v).map_err(|e| (k, e))?, k));
+        }
+        Ok(SmallVecMap {
+            indexs: self.indexs,
+            entries,
+        })
+    }
+
+    /// 转为只含值的`Vec<T>`，丢弃键；结果按entries中的物理顺序排列，并非按键排序
+    /// entries已溢出到堆上时，标准库的原地收集会尽量复用其内存
+    pub fn into_values(self) -> Vec<T> {
+        self.entries.into_vec().into_iter().map(|(v, _)| v).collect()
+    }
+
+    /// 检查indexs与entries是否一致，在所有构建下可用，返回描述第一处不一致的错误
+    /// 要求每个非空的`indexs[k]`指向键为`k`的值，且每个`entries[i]`的键在indexs中指回`i`
+    pub fn validate(&self) -> Result<(), String> {
+        for (k, i) in self.indexs.iter().enumerate() {
+            if i.is_null() {
+                continue;
+            }
+            match self.entries.get(*i as usize) {
+                None => return Err(format!("SmallVecMap, key {} points to slot {} out of {}", k, i, self.entries.len())),
+                Some((_, key)) if key.index() != k => return Err(format!("SmallVecMap, slot {} does not belong to key {}", i, k)),
+                _ => (),
+            }
+        }
+        for (i, (_, k)) in self.entries.iter().enumerate() {
+            if self.indexs.get(k.index()) != Some(&(i as u32)) {
+                return Err(format!("SmallVecMap, slot {} with key {:?} is not indexed", i, k));
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查indexs与entries是否一致，不一致时panic；仅在debug下检查，release下为空操作
+    pub fn debug_assert_consistent(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.validate() {
+            panic!("{}", e);
+        }
+    }
+
+    /// 取到SmallVecMap的长度
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+
+#[cfg(feature = "rayon")]
+impl<T: Sync, const N: usize, K: MapKey> SmallVecMap<T, N, K> {
+    /// 获取一个并行只读迭代器，产出`(key, &value)`
+    pub fn par_iter(&self) -> impl rayon::iter::IndexedParallelIterator<Item = (K, &T)> {
+        use rayon::prelude::*;
+        self.entries.as_slice().par_iter().map(|(v, k)| (*k, v))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Send, const N: usize, K: MapKey> SmallVecMap<T, N, K> {
+    /// 获取一个并行可写迭代器，产出`(key, &mut value)`
+    pub fn par_iter_mut(&mut self) -> impl rayon::iter::IndexedParallelIterator<Item = (K, &mut T)> {
+        use rayon::prelude::*;
+        self.entries.as_mut_slice().par_iter_mut().map(|(v, k)| (*k, v))
+    }
+}
+
+/// 为SmallVecMap实现Map
+impl<T, const N: usize, K: MapKey> Map for SmallVecMap<T, N, K> {
+	type Key = K;
+	type Val = T;
+    type Iter<'a> = Iter<'a, T, K> where Self: 'a;
+
+    #[inline]
+    fn iter(&self) -> Iter<'_, T, K> {
+        self.iter()
+    }
+
+    #[inline]
+    fn get(&self, key: &Self::Key) -> Option<&T> {
+        self.get(*key)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut T> {
+        self.get_mut(*key)
+    }
+
+    #[inline]
+    fn get2_mut(&mut self, a: &K, b: &K) -> (Option<&mut T>, Option<&mut T>) {
+        self.get2_mut(*a, *b)
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, key: &Self::Key) -> &T {
+        self.get_unchecked(*key)
+    }
+
+    #[inline]
+    unsafe fn get_unchecked_mut(&mut self, key: &Self::Key) -> &mut T {
+        self.get_unchecked_mut(*key)
+    }
+
+    #[inline]
+    unsafe fn remove_unchecked(&mut self, key: &Self::Key) -> T {
+        self.remove_unchecked(*key)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: Self::Key, val: T) -> Option<T> {
+        self.insert(key, val)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &Self::Key) -> Option<T> {
+        self.remove(*key)
+    }
+
+    #[inline]
+    fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> T) -> &mut T {
+        self.get_or_insert_with(key, f)
+    }
+
+    #[inline]
+    fn extend(&mut self, iter: impl IntoIterator<Item = (K, T)>) {
+        self.extend(iter)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.reserve(additional)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    #[inline]
+    fn retain(&mut self, mut f: impl FnMut(&Self::Key, &mut T) -> bool) {
+        self.retain(|k, v| f(&k, v))
+    }
+
+    #[inline]
+    fn contains(&self, key: &Self::Key) -> bool {
+        self.contains(*key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.indexs.capacity()
+    }
+    #[inline]
+    fn mem_size(&self) -> usize {
+        self.indexs.capacity() * std::mem::size_of::<u32>() + self.entries.capacity() * std::mem::size_of::<(T, K)>()
+	}
+	
+	fn with_capacity(capacity: usize) -> Self {
+		SmallVecMap::with_capacity(capacity)
+	}
+}
+
+/// 按键取值，键不存在时panic；下标类型就是键类型`K`，不再提供`Index<usize>`，以免`map[5]`这样的字面量无法推断类型
+impl<T, const N: usize, K: MapKey> Index<K> for SmallVecMap<T, N, K> {
+    type Output = T;
+
+    fn index(&self, index: K) -> &T {
+        unsafe { self.get_unchecked(index) }
+    }
+}
+
+impl<T, const N: usize, K: MapKey> IndexMut<K> for SmallVecMap<T, N, K> {
+    fn index_mut(&mut self, index: K) -> &mut T {
+        unsafe { self.get_unchecked_mut(index) }
+    }
+}
+
+/// 序列化为`key -> value`的映射，按键升序输出
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, const N: usize, K: MapKey + serde::Serialize> serde::Serialize for SmallVecMap<T, N, K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter_sorted())
+    }
+}
+
+/// 从`key -> value`的映射反序列化，逐个insert重建索引
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, const N: usize, K: MapKey + serde::Deserialize<'de>> serde::Deserialize<'de> for SmallVecMap<T, N, K> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<T, const N: usize, K>(std::marker::PhantomData<(T, K)>);
+
+        impl<'de, T: serde::Deserialize<'de>, const N: usize, K: MapKey + serde::Deserialize<'de>> serde::de::Visitor<'de> for MapVisitor<T, N, K> {
+            type Value = SmallVecMap<T, N, K>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of integer keys")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut map = SmallVecMap::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some((k, v)) = access.next_entry::<K, T>()? {
+                    if k.is_null() {
+                        return Err(serde::de::Error::custom("SmallVecMap key can not be null"));
+                    }
+                    map.insert(k, v);
+                }
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor(std::marker::PhantomData))
+    }
+}
+
+/// 编码为`u32`长度加按键升序排列的`(key, value)`
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshSerialize, const N: usize, K: MapKey + borsh::BorshSerialize> borsh::BorshSerialize for SmallVecMap<T, N, K> {
+    fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
+        borsh::BorshSerialize::serialize(&(self.len() as u32), writer)?;
+        for (k, v) in self.iter_sorted() {
+            borsh::BorshSerialize::serialize(&k, writer)?;
+            borsh::BorshSerialize::serialize(v, writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshDeserialize, const N: usize, K: MapKey + borsh::BorshDeserialize> borsh::BorshDeserialize for SmallVecMap<T, N, K> {
+    fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        let len = <u32 as borsh::BorshDeserialize>::deserialize_reader(reader)? as usize;
+        // 长度来自外部数据，预分配时设置上限
+        let mut map = SmallVecMap::new();
+        map.entries.reserve(len.min(4096));
+        for _ in 0..len {
+            let k = K::deserialize_reader(reader)?;
+            if k.is_null() {
+                return Err(borsh::io::Error::new(borsh::io::ErrorKind::InvalidData, "SmallVecMap key can not be null"));
+            }
+            map.insert(k, T::deserialize_reader(reader)?);
+        }
+        Ok(map)
+    }
+}
+
+/// 归档为按键排序的`ArchivedBTreeMap`，无需反序列化即可按键查询，仅支持`u32`键
+#[cfg(feature = "rkyv")]
+impl<T: rkyv::Archive, const N: usize> rkyv::Archive for SmallVecMap<T, N, u32> {
+    type Archived = rkyv::collections::btree_map::ArchivedBTreeMap<rkyv::Archived<u32>, T::Archived>;
+    type Resolver = rkyv::collections::btree_map::BTreeMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: rkyv::Place<Self::Archived>) {
+        Self::Archived::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<T, S, const N: usize> rkyv::Serialize<S> for SmallVecMap<T, N, u32>
+where
+    T: rkyv::Serialize<S>,
+    S: rkyv::rancor::Fallible + rkyv::ser::Allocator + rkyv::ser::Writer + ?Sized,
+    S::Error: rkyv::rancor::Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let sorted: Vec<(u32, &T)> = self.iter_sorted().collect();
+        Self::Archived::serialize_from_ordered_iter::<_, _, _, u32, T, _>(sorted.into_iter(), serializer)
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<T, D, const N: usize> rkyv::Deserialize<SmallVecMap<T, N, u32>, D>
+    for rkyv::collections::btree_map::ArchivedBTreeMap<rkyv::Archived<u32>, T::Archived>
+where
+    T: rkyv::Archive,
+    T::Archived: rkyv::Deserialize<T, D>,
+    D: rkyv::rancor::Fallible + ?Sized,
+    D::Error: rkyv::rancor::Source,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<SmallVecMap<T, N, u32>, D::Error> {
+        let mut map = SmallVecMap::new();
+        map.entries.reserve(self.len());
+        let r = self.visit(|k, v| {
+            let k = k.to_native();
+            if k.is_null() {
+                return std::ops::ControlFlow::Break(rkyv::rancor::Source::new(ArchivedNullKey));
+            }
+            match v.deserialize(deserializer) {
+                Ok(v) => {
+                    map.insert(k, v);
+                    std::ops::ControlFlow::Continue(())
+                }
+                Err(e) => std::ops::ControlFlow::Break(e),
+            }
+        });
+        match r {
+            Some(e) => Err(e),
+            None => Ok(map),
+        }
+    }
+}
+
+/// 归档数据中出现了`u32::null()`键
+#[cfg(feature = "rkyv")]
+#[derive(Debug)]
+struct ArchivedNullKey;
+
+#[cfg(feature = "rkyv")]
+impl std::fmt::Display for ArchivedNullKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SmallVecMap key can not be null")
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl std::error::Error for ArchivedNullKey {}
+
+impl<T, const N: usize, K: MapKey> IntoIterator for SmallVecMap<T, N, K> {
+    type Item = (K, T);
+    type IntoIter = IntoIter<T, N, K>;
+
+    fn into_iter(self) -> IntoIter<T, N, K> {
+        IntoIter(self.entries.into_iter())
+    }
+}
+
+/// SmallVecMap的所有权迭代器，产出`(key, value)`
+pub struct IntoIter<T, const N: usize, K = u32>(smallvec::IntoIter<[(T, K); N]>);
+
+impl<T, const N: usize, K> Iterator for IntoIter<T, N, K> {
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, T)> {
+        self.0.next().map(|(v, k)| (k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, const N: usize, K> DoubleEndedIterator for IntoIter<T, N, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, T)> {
+        self.0.next_back().map(|(v, k)| (k, v))
+    }
+}
+
+impl<T, const N: usize, K> ExactSizeIterator for IntoIter<T, N, K> {}
+
+/// SmallVecMap的移出迭代器，产出`(key, value)`
+pub struct Drain<'a, T, const N: usize, K = u32>(smallvec::Drain<'a, [(T, K); N]>);
+
+impl<T, const N: usize, K> Iterator for Drain<'_, T, N, K> {
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, T)> {
+        self.0.next().map(|(v, k)| (k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, const N: usize, K> DoubleEndedIterator for Drain<'_, T, N, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, T)> {
+        self.0.next_back().map(|(v, k)| (k, v))
+    }
+}
+
+impl<T, const N: usize, K> ExactSizeIterator for Drain<'_, T, N, K> {}
+
+/// SmallVecMap的条件移出迭代器，产出`(key, value)`，见`SmallVecMap::drain_filter`
+pub struct DrainFilter<'a, T, const N: usize, F: FnMut(K, &mut T) -> bool, K: MapKey = u32> {
+    map: &'a mut SmallVecMap<T, N, K>,
+    // 下一个待检查的entries位置
+    cur: usize,
+    pred: F,
+}
+
+impl<T, const N: usize, F: FnMut(K, &mut T) -> bool, K: MapKey> Iterator for DrainFilter<'_, T, N, F, K> {
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<(K, T)> {
+        let map = &mut *self.map;
+        while self.cur < map.entries.len() {
+            let (v, k) = &mut map.entries[self.cur];
+            if !(self.pred)(*k, v) {
+                self.cur += 1;
+                continue;
+            }
+            map.indexs[k.index()] = u32::null();
+            // 从尾部交换元素到当前位置，下一次继续检查该位置
+            let (v, k) = map.entries.swap_remove(self.cur);
+            if self.cur < map.entries.len() {
+                map.indexs[map.entries[self.cur].1.index()] = self.cur as u32;
+            }
+            map.trim_tail(k);
+            return Some((k, v));
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.entries.len() - self.cur))
+    }
+}
+
+impl<T, const N: usize, F: FnMut(K, &mut T) -> bool, K: MapKey> Drop for DrainFilter<'_, T, N, F, K> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<'a, T, const N: usize, K: MapKey> IntoIterator for &'a SmallVecMap<T, N, K> {
+    type Item = (K, &'a T);
+    type IntoIter = Iter<'a, T, K>;
+
+    fn into_iter(self) -> Iter<'a, T, K> {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize, K: MapKey> IntoIterator for &'a mut SmallVecMap<T, N, K> {
+    type Item = (K, &'a mut T);
+    type IntoIter = IterMut<'a, T, K>;
+
+    fn into_iter(self) -> IterMut<'a, T, K> {
+        self.iter_mut_keyed()
+    }
+}
+
+/// SmallVecMap的只读迭代器，产出`(key, &value)`
+pub struct Iter<'a, T, K = u32>(slice::Iter<'a, (T, K)>);
+
+impl<'a, T, K: Copy> Iterator for Iter<'a, T, K> {
+    type Item = (K, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, &'a T)> {
+        self.0.next().map(|(v, k)| (*k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T, K: Copy> DoubleEndedIterator for Iter<'a, T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, &'a T)> {
+        self.0.next_back().map(|(v, k)| (*k, v))
+    }
+}
+
+impl<T, K: Copy> ExactSizeIterator for Iter<'_, T, K> {}
+
+/// SmallVecMap的可写迭代器，产出`(key, &mut value)`
+pub struct IterMut<'a, T, K = u32>(slice::IterMut<'a, (T, K)>);
+
+impl<'a, T, K: Copy> Iterator for IterMut<'a, T, K> {
+    type Item = (K, &'a mut T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, &'a mut T)> {
+        self.0.next().map(|(v, k)| (*k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T, K: Copy> DoubleEndedIterator for IterMut<'a, T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, &'a mut T)> {
+        self.0.next_back().map(|(v, k)| (*k, v))
+    }
+}
+
+impl<T, K: Copy> ExactSizeIterator for IterMut<'_, T, K> {}
+
+
+#[cfg(test)]
+use std::time::Instant;
+#[test]
+fn test_time(){
+    let mut map: SmallVecMap<u32, 16> = SmallVecMap::new();
+    let _cc = map.clone();
+    let mut arr = Vec::with_capacity(100000);
+    let time = Instant::now();
+    for i in 0..10000 {
+        arr.push(i as f32 + 0.5);
+    }
+    println!("insert vec time: {:?}", Instant::now() - time);
+
+    let time = Instant::now();
+    for i in 1..10001 {
+        map.insert(i, i);
+    }
+    println!("insert SmallVecMap time: {:?}", Instant::now() - time);
+
+
+    let mut map: SmallVecMap<f32, 16> = SmallVecMap::new();
+
+    let time = Instant::now();
+    for i in 1..10001 {
+        map.insert(i, 1.0);
+    }
+    println!("insert SmallVecMap time: {:?}", Instant::now() - time);
+
+}
+#[test]
+fn test(){
+    let mut map: SmallVecMap<u32, 8> = SmallVecMap::new();
+    for i in 1..71{
+        map.insert(i, i);
+        println!("map------{:?}", map);
+    }
+
+    map.remove(30);
+    println!("r 30------{:?}", map);
+
+    map.remove(31);
+    println!("r 31------{:?}", map);
+
+    map.remove(69);
+    println!("r 69------{:?}", map);
+
+    map.remove(70);
+    println!("r 70------{:?}", map);
+
+    assert_eq!(map.contains(0), false);
+    assert_eq!(map.contains(1), true);
+    assert_eq!(map.contains(71), false);
+    assert_eq!(map.contains(72), false);
+
+    assert_eq!(map.get(0), None);
+    assert_eq!(map.get(1), Some(&1));
+    assert_eq!(map.get(50), Some(&50));
+    assert_eq!(map.get(70), None);
+    assert_eq!(map.get(72), None);
+
+
+    assert_eq!(map.get_mut(0), None);
+    assert_eq!(map.get_mut(64), Some(&mut 64));
+    assert_eq!(map.get_mut(30), None);
+    assert_eq!(map.get_mut(20), Some(&mut 20));
+    assert_eq!(map.get_mut(75), None);
+
+    assert_eq!(unsafe{map.get_unchecked(2)}, &2);
+    assert_eq!(unsafe{map.get_unchecked(9)}, &9);
+    assert_eq!(unsafe{map.get_unchecked(55)}, &55);
+    assert_eq!(unsafe{map.get_unchecked(60)}, &60);
+
+    assert_eq!(unsafe{map.get_unchecked_mut(44)}, &mut 44);
+    assert_eq!(unsafe{map.get_unchecked_mut(33)}, &mut 33);
+    assert_eq!(unsafe{map.get_unchecked_mut(7)}, &mut 7);
+}
+
+#[test]
+fn test_contains_all(){
+    let mut map: SmallVecMap<u32, 8> = (1..71).map(|i| (i, i)).collect();
+    map.remove(30);
+    assert!(map.contains_all(&[1, 2, 50]));
+    assert!(!map.contains_all(&[1, 30]));
+    assert!(map.contains_all(&[]));
+    assert!(map.contains_any(&[0, 30, 50]));
+    assert!(!map.contains_any(&[0, 30, 100]));
+    assert!(!map.contains_any(&[]));
+}
+
+#[test]
+fn test_get_or_err(){
+    let mut map: SmallVecMap<u32, 8> = (1..71).map(|i| (i, i)).collect();
+    map.remove(30);
+    assert_eq!(map.get_or_err(50), Ok(&50));
+    assert_eq!(map.get_or_err(30), Err(MissingKey(30)));
+    assert_eq!(map.get_mut_or_err(64), Ok(&mut 64));
+    assert_eq!(map.get_mut_or_err(75).unwrap_err().to_string(), "SmallVecMap key 75 not found");
+}
+
+#[test]
+fn test_remove_at_slot(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    let slot = map.entries_slice().iter().position(|(_, k)| *k == 7).unwrap();
+    assert_eq!(unsafe{map.remove_at_slot(slot)}, (7, 7));
+    assert!(!map.contains(7));
+    assert_eq!(map.len(), 9);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_iter_slots(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i * 10)).collect();
+    map.remove(3);
+    assert_eq!(map.iter_slots().count(), map.len());
+    for (slot, k, v) in map.iter_slots() {
+        assert_eq!(map.entries_slice()[slot].1, k);
+        assert_eq!(map.get(k), Some(v));
+    }
+}
+
+#[test]
+fn test_validate(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    map.remove(5);
+    assert_eq!(map.validate(), Ok(()));
+    assert_eq!(SmallVecMap::<u32, 4>::new().validate(), Ok(()));
+    // 人为破坏索引：让entries[0]的键指向另一个已存在的键
+    map.entries[0].1 = 7;
+    assert!(map.validate().is_err());
+}
+
+#[test]
+fn test_insert_sparse(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::with_capacity(4);
+    assert_eq!(map.insert(10_000, 1), None);
+    assert_eq!(map.insert(2, 2), None);
+    assert_eq!(map.insert(10_000, 3), Some(1));
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(10_000), Some(&3));
+    assert_eq!(map.get(9_999), None);
+    assert_eq!(map.get(2), Some(&2));
+}
+
+#[test]
+fn test_mem_size(){
+    let mut map: SmallVecMap<u64, 4> = SmallVecMap::new();
+    let inline = Map::mem_size(&map);
+    assert_eq!(inline, 4 * std::mem::size_of::<(u64, u32)>());
+    map.insert(50, 1);
+    assert!(map.index_capacity() >= 51);
+    assert_eq!(Map::mem_size(&map), map.index_capacity() * std::mem::size_of::<u32>() + inline);
+    map.extend((0..8).map(|i| (i, i as u64)));
+    assert!(map.is_spilled());
+    assert_eq!(Map::mem_size(&map), map.index_capacity() * std::mem::size_of::<u32>() + map.entries_capacity() * std::mem::size_of::<(u64, u32)>());
+}
+
+#[test]
+fn test_try_replace(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.insert(3, 30);
+    assert_eq!(map.try_replace(3, 31), Ok(30));
+    assert_eq!(map.get(3), Some(&31));
+    assert_eq!(map.try_replace(4, 40), Err(40));
+    assert_eq!(map.try_replace(100, 1), Err(1));
+    assert_eq!(map.get(4), None);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_contains_value(){
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from_pairs([(3, "a"), (9, "b"), (20, "c")]);
+    assert!(map.contains_value(&"b"));
+    assert!(!map.contains_value(&"d"));
+    assert_eq!(map.find_key(|v| *v == "c"), Some(20));
+    assert_eq!(map.find_key(|v| v.is_empty()), None);
+    map.remove(20);
+    assert!(!map.contains_value(&"c"));
+    assert_eq!(map.find_key(|v| *v == "c"), None);
+}
+
+#[test]
+fn test_entries_slice(){
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("a", 7), ("b", 2), ("c", 30)]);
+    assert_eq!(map.entries_slice(), &[("a", 7), ("b", 2), ("c", 30)]);
+    map.remove(7);
+    assert_eq!(map.entries_slice(), &[("c", 30), ("b", 2)]);
+    assert!(SmallVecMap::<u32, 4>::new().entries_slice().is_empty());
+}
+
+#[test]
+fn test_remove_entry(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i * 10)).collect();
+    assert_eq!(map.remove_entry(3), Some((3, 30)));
+    assert_eq!(map.remove_entry(3), None);
+    assert_eq!(map.remove_entry(100), None);
+    assert_eq!(map.remove_entry(5), Some((5, 50)));
+    assert_eq!(map.status(5), Slot::OutOfRange);
+    assert_eq!(map.len(), 3);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_with_capacity_and_max_key(){
+    let map: SmallVecMap<u32, 4> = SmallVecMap::with_capacity_and_max_key(16, 100);
+    assert_eq!(map.indexs.len(), 101);
+    assert!(map.entries.capacity() >= 16);
+    assert!(map.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "can not be null")]
+fn test_with_capacity_and_max_key_null(){
+    let _: SmallVecMap<u32, 4> = SmallVecMap::with_capacity_and_max_key(16, u32::null());
+}
+
+#[test]
+fn test_insert_tracked(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::with_capacity_and_max_key(16, 100);
+    assert_eq!(map.insert_tracked(100, 1), (None, false));
+    assert_eq!(map.insert_tracked(100, 2), (Some(1), false));
+    assert_eq!(map.insert_tracked(101, 1), (None, true));
+}
+
+#[test]
+fn test_get_key_value(){
+    let map: SmallVecMap<u32, 4> = [(2, 2), (10_000, 3)].into_iter().collect();
+    assert_eq!(map.get_key_value(10_000), Some((10_000, &3)));
+    assert_eq!(map.get_key_value(3), None);
+    assert_eq!(map.get_key_value(20_000), None);
+}
+
+#[test]
+fn test_try_insert(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.insert(2, 2);
+    let e = map.try_insert(2, 5).unwrap_err();
+    assert_eq!((*e.entry, e.value), (2, 5));
+    assert_eq!(map.try_insert(3, 5).map(|v| *v).ok(), Some(5));
+    assert_eq!(map.get(2), Some(&2));
+    assert_eq!(map.get(3), Some(&5));
+}
+
+#[test]
+fn test_index(){
+    let mut map: SmallVecMap<u32, 4> = [(2, 2), (10_000, 3)].into_iter().collect();
+    let key: u32 = 2;
+    map[key] += 1;
+    assert_eq!(map[key], 3);
+    assert_eq!(map[10_000], 3);
+
+    let map: SmallVecMap<&str, 4, u64> = SmallVecMap::from_pairs([(2, "c")]);
+    assert_eq!(map[2], "c");
+}
+
+#[test]
+#[should_panic]
+fn test_index_missing(){
+    let map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    let _ = map[3];
+}
+
+#[test]
+fn test_from_vec(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("a", 7), ("b", 2), ("c", 30)]);
+    assert_eq!(map.get(2), Some(&"b"));
+    assert_eq!(map.get(7), Some(&"a"));
+    assert_eq!(map.get(30), Some(&"c"));
+    assert_eq!(map.get(0), None);
+
+    let mut sorted: SmallVecMap<&str, 4> = SmallVecMap::from_sorted_by_key(vec![("b", 2), ("a", 7), ("c", 30)]);
+    assert!(sorted == map);
+    sorted.remove(2);
+    assert_eq!(sorted.get(30), Some(&"c"));
+    assert_eq!(sorted.get(7), Some(&"a"));
+}
+
+#[test]
+fn test_into_vec(){
+    let v = vec![("a", 7), ("b", 2), ("c", 30)];
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from(v.clone());
+    assert_eq!(map.clone().into_vec(), v);
+    map.remove(2);
+    assert_eq!(map.into_vec(), vec![("a", 7), ("c", 30)]);
+}
+
+#[test]
+fn test_debug(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from_pairs([(30, "c"), (2, "b"), (7, "a")]);
+    assert_eq!(format!("{:?}", map), r#"{2: "b", 7: "a", 30: "c"}"#);
+    assert_eq!(format!("{:?}", SmallVecMap::<u32, 4>::new()), "{}");
+}
+
+#[test]
+fn test_status(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from_pairs([(7, "a"), (2, "b"), (30, "c")]);
+    assert_eq!(map.status(2), Slot::Occupied);
+    assert_eq!(map.status(0), Slot::Vacant);
+    assert_eq!(map.status(31), Slot::OutOfRange);
+    assert_eq!(map.len(), 3);
+}
+
+#[test]
+fn test_from_pairs(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from_pairs([(7, "x"), (2, "b"), (30, "c"), (7, "a")]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(2), Some(&"b"));
+    assert_eq!(map.get(7), Some(&"a"));
+    assert_eq!(map.get(30), Some(&"c"));
+}
+
+#[test]
+fn test_extend_ascending(){
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from_pairs([(2, "b")]);
+    map.extend_ascending([(7, "a"), (30, "c")]);
+    map.debug_assert_consistent();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(7), Some(&"a"));
+    assert_eq!(map.max_key(), Some(30));
+    assert_eq!(map.indexs.len(), 31);
+    map.extend_ascending([]);
+    assert_eq!(map.len(), 3);
+
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| map.extend_ascending([(40, "d"), (30, "e")])));
+    assert!(r.is_err());
+    map.debug_assert_consistent();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.indexs.len(), 31);
+
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::with_capacity_and_max_key(4, 50);
+    map.extend_ascending([(3, "a")]);
+    map.debug_assert_consistent();
+    assert_eq!(map.indexs.len(), 51);
+}
+
+#[test]
+fn test_from_array(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from([(7, "x"), (2, "b"), (30, "c"), (7, "a")]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(7), Some(&"a"));
+    assert_eq!(map.get(30), Some(&"c"));
+}
+
+#[test]
+fn test_default_n(){
+    let mut map: DefaultSmallVecMap<&str> = SmallVecMap::from_pairs([(7, "a")]);
+    let _: &SmallVecMap<&str> = &map;
+    for i in 0..8{
+        map.insert(i + 10, "b");
+    }
+    assert_eq!(map.len(), 9);
+    assert!(map.is_spilled());
+}
+
+#[test]
+fn test_map_values(){
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("bb", 2), ("a", 7), ("ccc", 30)]);
+    map.remove(2);
+    let lens = map.map_values(|v| v.len());
+    lens.debug_assert_consistent();
+    assert_eq!(lens.get(30), Some(&3));
+    assert_eq!(lens.get(7), Some(&1));
+    assert_eq!(lens.get(2), None);
+}
+
+#[test]
+fn test_try_map_values(){
+    let map: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("c", 30), ("a", 7)]);
+    let parsed = map.clone().try_map_values(|v| if v == "a" { Ok(1) } else { Err(v) });
+    assert_eq!(parsed.unwrap_err(), (30, "c"));
+    let parsed = map.try_map_values(|v| Ok::<_, ()>(v.to_string())).unwrap();
+    parsed.debug_assert_consistent();
+    assert_eq!(parsed.get(7).map(String::as_str), Some("a"));
+    assert_eq!(parsed.get(30).map(String::as_str), Some("c"));
+}
+
+#[test]
+fn test_into_values(){
+    let mut map: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("b", 2), ("a", 7), ("c", 30)]);
+    map.remove(2);
+    assert_eq!(map.into_values(), vec!["c", "a"]);
+    assert!(SmallVecMap::<&str, 4>::new().into_values().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "duplicate key")]
+fn test_from_vec_duplicate_key(){
+    let _: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("a", 3), ("b", 3)]);
 }
+
 #[test]
-fn test(){
-    let mut map: SmallVecMap<u32, 8> = SmallVecMap::new();
-    for i in 1..71{
+#[should_panic(expected = "null key")]
+fn test_from_vec_null_key(){
+    let _: SmallVecMap<&str, 4> = SmallVecMap::from(vec![("a", 3), ("b", u32::null())]);
+}
+
+#[test]
+#[should_panic(expected = "can not be null")]
+fn test_insert_null_key(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.insert(u32::null(), 1);
+}
+
+#[test]
+fn test_into_iter(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    for i in 1..11{
+        map.insert(i, i * 10);
+    }
+    map.remove(3);
+    map.remove(7);
+
+    let mut r: Vec<(u32, u32)> = map.into_iter().collect();
+    r.sort();
+    assert_eq!(r, vec![(1, 10), (2, 20), (4, 40), (5, 50), (6, 60), (8, 80), (9, 90), (10, 100)]);
+}
+
+#[test]
+fn test_ref_iter(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    for i in 1..6{
         map.insert(i, i);
-        println!("map------{:?}", map);
     }
+    for (k, v) in &mut map {
+        *v += k;
+    }
+    let mut r: Vec<(u32, u32)> = (&map).into_iter().map(|(k, v)| (k, *v)).collect();
+    r.sort();
+    assert_eq!(r, vec![(1, 2), (2, 4), (3, 6), (4, 8), (5, 10)]);
+}
 
-    map.remove(30);
-    println!("r 30------{:?}", map);
+#[test]
+fn test_values(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    map.remove(3);
+    let mut r: Vec<u32> = map.values().copied().collect();
+    r.sort();
+    assert_eq!(r, vec![1, 2, 4, 5]);
+    for v in map.values_mut() {
+        *v *= 10;
+    }
+    assert_eq!(map.get(1), Some(&10));
+    assert_eq!(map.get(5), Some(&50));
+    assert_eq!(map.get(3), None);
+    assert_eq!(map.values().count(), 4);
+}
 
-    map.remove(31);
-    println!("r 31------{:?}", map);
+#[test]
+fn test_exact_size(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    let mut iter = map.iter();
+    assert_eq!(iter.len(), 5);
+    iter.next();
+    assert_eq!(iter.size_hint(), (4, Some(4)));
+    assert_eq!(map.iter_mut_keyed().len(), 5);
+    assert_eq!(map.drain().len(), 5);
+}
 
-    map.remove(69);
-    println!("r 69------{:?}", map);
+#[test]
+fn test_iter_rev(){
+    let map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    let back: Vec<u32> = map.iter().rev().map(|(k, _)| k).collect();
+    assert_eq!(back, vec![5, 4, 3, 2, 1]);
+    let mut iter = map.iter();
+    assert_eq!(iter.next(), Some((1, &1)));
+    assert_eq!(iter.next_back(), Some((5, &5)));
+    assert_eq!(iter.count(), 3);
+}
 
-    map.remove(70);
-    println!("r 70------{:?}", map);
+#[test]
+fn test_iter(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    map.remove(3);
+    let mut r: Vec<(u32, &u32)> = map.iter().collect();
+    r.sort();
+    assert_eq!(r, vec![(1, &1), (2, &2), (4, &4), (5, &5)]);
+}
 
-    assert_eq!(map.contains(0), false);
-    assert_eq!(map.contains(1), true);
-    assert_eq!(map.contains(71), false);
-    assert_eq!(map.contains(72), false);
+#[test]
+fn test_iter_mut_keyed(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    for (k, v) in map.iter_mut_keyed() {
+        *v += k;
+    }
+    assert_eq!(map.iter_mut_keyed().len(), 5);
+    for i in 1..6{
+        assert_eq!(map.get(i), Some(&(i * 2)));
+    }
+}
 
-    assert_eq!(map.get(0), None);
-    assert_eq!(map.get(1), Some(&1));
-    assert_eq!(map.get(50), Some(&50));
-    assert_eq!(map.get(70), None);
-    assert_eq!(map.get(72), None);
+#[test]
+fn test_iter_mut(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i)).collect();
+    map.iter_mut().for_each(|v| *v *= 2);
+    assert_eq!(map.iter_mut().len(), 5);
+    for i in 1..6{
+        assert_eq!(map.get(i), Some(&(i * 2)));
+    }
+}
 
+#[test]
+fn test_apply_all(){
+    let mut map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i * 2)).collect();
+    map.apply_all(|k, v| *v += k);
+    for i in 1..6{
+        assert_eq!(map.get(i), Some(&(i * 3)));
+    }
+}
 
-    assert_eq!(map.get_mut(0), None);
-    assert_eq!(map.get_mut(64), Some(&mut 64));
-    assert_eq!(map.get_mut(30), None);
-    assert_eq!(map.get_mut(20), Some(&mut 20));
-    assert_eq!(map.get_mut(75), None);
+#[test]
+fn test_fold(){
+    let map: SmallVecMap<u32, 4> = (1..6).map(|i| (i, i * 3)).collect();
+    assert_eq!(map.fold(0, |acc, k, v| acc + v - k), 30);
+    assert_eq!(SmallVecMap::<u32, 4>::new().fold(1, |acc, _, v| acc + v), 1);
+}
 
-    assert_eq!(unsafe{map.get_unchecked(2)}, &2);
-    assert_eq!(unsafe{map.get_unchecked(9)}, &9);
-    assert_eq!(unsafe{map.get_unchecked(55)}, &55);
-    assert_eq!(unsafe{map.get_unchecked(60)}, &60);
+#[test]
+fn test_from_iter(){
+    let map: SmallVecMap<&str, 4> = vec![(3, "a"), (10, "b"), (3, "c")].into_iter().collect();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(3), Some(&"c"));
+    assert_eq!(map.get(10), Some(&"b"));
 
-    assert_eq!(unsafe{map.get_unchecked_mut(44)}, &mut 44);
-    assert_eq!(unsafe{map.get_unchecked_mut(33)}, &mut 33);
-    assert_eq!(unsafe{map.get_unchecked_mut(7)}, &mut 7);
+    let map: SmallVecMap<u32, 4> = std::iter::empty().collect();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_insert_or_modify(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert!(map.insert_or_modify(7, 1, |v| *v += 1));
+    assert!(!map.insert_or_modify(7, 1, |v| *v += 1));
+    assert_eq!(map.get(7), Some(&2));
+}
+
+#[test]
+fn test_get_or_default(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.insert(7, 2);
+    *map.get_or_default(7) += 1;
+    *map.get_or_default(8) += 1;
+    assert_eq!(map.get(7), Some(&3));
+    assert_eq!(map.get(8), Some(&1));
+}
+
+#[test]
+fn test_get_or_insert_with(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.insert(7, 2);
+    let mut called = false;
+    *map.get_or_insert_with(7, || { called = true; 0 }) += 1;
+    assert!(!called);
+    assert_eq!(map.get(7), Some(&3));
+    *map.get_or_insert_with(9, || 5) += 1;
+    assert_eq!(map.get(9), Some(&6));
+    assert_eq!(map.len(), 2);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_extend(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    map.extend([(1, 1), (5000, 2), (20, 3), (5000, 4)]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(5000), Some(&4));
+    assert_eq!(map.get(20), Some(&3));
+}
+
+#[test]
+fn test_clear(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    Map::clear(&mut map);
+    assert!(Map::is_empty(&map));
+    assert_eq!(map.get(5), None);
+    assert_eq!(map.indexs.len(), 0);
+    assert_eq!(Map::iter(&map).count(), 0);
+}
+
+#[test]
+fn test_reserve(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    Map::reserve(&mut map, 100);
+    assert!(Map::capacity(&map) >= 100);
+}
+
+#[test]
+fn test_eq(){
+    let mut a: SmallVecMap<u32, 4> = SmallVecMap::new();
+    let mut b: SmallVecMap<u32, 4> = SmallVecMap::new();
+    for i in 1..8{
+        a.insert(i, i);
+        b.insert(8 - i, 8 - i);
+    }
+    a.remove(2);
+    b.remove(2);
+    assert!(a == b);
+
+    b.insert(2, 2);
+    assert!(a != b);
+    a.insert(2, 3);
+    assert!(a != b);
+}
+
+#[test]
+fn test_clone_from(){
+    let template: SmallVecMap<u32, 2> = (1..9).map(|i| (i, i)).collect();
+    let mut scratch: SmallVecMap<u32, 2> = (1..33).map(|i| (i, 0)).collect();
+    let (indexs, entries) = (scratch.indexs.as_ptr(), scratch.entries.as_ptr());
+    scratch.clone_from(&template);
+    assert!(scratch == template);
+    assert_eq!(scratch.indexs.as_ptr(), indexs);
+    assert_eq!(scratch.entries.as_ptr(), entries);
+}
+
+#[test]
+fn test_hash(){
+    use std::collections::hash_map::DefaultHasher;
+    let hash = |m: &SmallVecMap<u32, 4>| {
+        let mut h = DefaultHasher::new();
+        m.hash(&mut h);
+        h.finish()
+    };
+    let a: SmallVecMap<u32, 4> = (1..8).map(|i| (i, i)).collect();
+    let mut b: SmallVecMap<u32, 4> = (1..8).rev().map(|i| (i, i)).collect();
+    assert_eq!(hash(&a), hash(&b));
+    b.insert(3, 4);
+    assert_ne!(hash(&a), hash(&b));
+}
+
+#[test]
+fn test_get_disjoint_mut(){
+    let mut map: SmallVecMap<u32, 4> = (1..8).map(|i| (i, i)).collect();
+    let [a, b, c] = map.get_disjoint_mut([1, 7, 3]).unwrap();
+    std::mem::swap(a, b);
+    *c += 10;
+    assert_eq!(map.get(1), Some(&7));
+    assert_eq!(map.get(7), Some(&1));
+    assert_eq!(map.get(3), Some(&13));
+    assert!(map.get_disjoint_mut([1, 1]).is_none());
+    assert!(map.get_disjoint_mut([1, 8]).is_none());
+    assert!(map.get_disjoint_mut([1, 100]).is_none());
+}
+
+#[test]
+fn test_swap(){
+    let mut map: SmallVecMap<u32, 4> = (1..8).map(|i| (i, i)).collect();
+    assert!(map.swap(1, 7));
+    assert_eq!(map.get(1), Some(&7));
+    assert_eq!(map.get(7), Some(&1));
+    assert!(map.swap(3, 3));
+    assert_eq!(map.get(3), Some(&3));
+    assert!(!map.swap(3, 100));
+    assert_eq!(map.get(3), Some(&3));
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_get2_mut(){
+    let mut map: SmallVecMap<u32, 4> = (1..8).map(|i| (i, i)).collect();
+    let (a, b) = map.get2_mut(1, 7);
+    std::mem::swap(a.unwrap(), b.unwrap());
+    assert_eq!(map.get(1), Some(&7));
+    assert_eq!(map.get2_mut(1, 100), (Some(&mut 7), None));
+    assert_eq!(map.get2_mut(100, 1), (None, Some(&mut 7)));
+    assert_eq!(map.get2_mut(1, 1), (None, None));
+    assert_eq!(Map::get2_mut(&mut map, &7, &2), (Some(&mut 1), Some(&mut 2)));
+}
+
+#[test]
+fn test_get_many_unchecked_mut(){
+    let mut map: SmallVecMap<u32, 4> = (1..8).map(|i| (i, i)).collect();
+    let [a, b, c] = unsafe { map.get_many_unchecked_mut([1, 2, 5]) };
+    std::mem::swap(a, b);
+    *c += 10;
+    assert_eq!(map.get(1), Some(&2));
+    assert_eq!(map.get(2), Some(&1));
+    assert_eq!(map.get(5), Some(&15));
+}
+
+#[test]
+fn test_retain_map(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    map.retain_map(|k, v| if k % 2 == 0 { Some((k / 2, v * 10)) } else { None });
+    assert_eq!(map.len(), 5);
+    for i in 1..11{
+        assert_eq!(map.get(i).copied(), if i <= 5 { Some(i * 20) } else { None });
+    }
+}
+
+#[test]
+fn test_debug_assert_consistent(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    map.debug_assert_consistent();
+    map.retain(|k, _| k % 3 != 0);
+    map.debug_assert_consistent();
+    map.retain_map(|k, v| if k % 2 == 0 { Some((k / 2, v)) } else { None });
+    map.debug_assert_consistent();
+    let other = map.split_off(5);
+    map.debug_assert_consistent();
+    other.debug_assert_consistent();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic]
+fn test_debug_assert_inconsistent(){
+    let mut map: SmallVecMap<u32, 4> = (1..5).map(|i| (i, i)).collect();
+    map.indexs[2] = u32::null();
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_split_off(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    let other = map.split_off(15);
+    assert_eq!(map.len(), 14);
+    assert_eq!(other.len(), 6);
+    assert_eq!(map.indexs.len(), 15);
+    assert_eq!(other.indexs.len(), 21);
+    for i in 1..21{
+        assert_eq!(map.get(i), if i < 15 { Some(&i) } else { None });
+        assert_eq!(other.get(i), if i >= 15 { Some(&i) } else { None });
+    }
+
+    let mut map: SmallVecMap<u32, 4> = [(1, 1), (2, 2), (10, 10)].into_iter().collect();
+    let other = map.split_off(8);
+    map.debug_assert_consistent();
+    assert_eq!(map.indexs.len(), 3);
+    assert_eq!(map.status(7), Slot::OutOfRange);
+    assert_eq!(map.fragmentation(), (1, 3));
+    assert_eq!(other.get(10), Some(&10));
+}
+
+#[test]
+fn test_truncate_keys(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    map.truncate_keys(14);
+    map.debug_assert_consistent();
+    assert_eq!(map.len(), 14);
+    assert_eq!(map.indexs.len(), 15);
+    assert_eq!(map.get(14), Some(&14));
+    assert_eq!(map.get(15), None);
+}
+
+#[test]
+fn test_retain(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    map.remove(5);
+    map.retain(|k, v| {
+        *v += 1;
+        k % 3 != 0
+    });
+    assert_eq!(map.len(), 13);
+    for i in 1..21{
+        match (i % 3, i) {
+            (0, _) | (_, 5) => assert_eq!(map.get(i), None),
+            _ => assert_eq!(map.get(i), Some(&(i + 1))),
+        }
+    }
+}
+
+#[test]
+fn test_drain(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    let mut r: Vec<(u32, u32)> = map.drain().collect();
+    r.sort();
+    assert_eq!(r, (1..11).map(|i| (i, i)).collect::<Vec<_>>());
+    assert!(map.is_empty());
+    assert!(!map.contains(1));
+
+    map.extend((1..11).map(|i| (i, i)));
+    assert_eq!(map.drain().take(3).count(), 3);
+    assert!(map.is_empty());
+    assert_eq!(map.get(5), None);
+    map.insert(5, 5);
+    assert_eq!(map.get(5), Some(&5));
+}
+
+#[test]
+fn test_drain_filter(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i + 1)).collect();
+    let mut drained: Vec<(u32, u32)> = map.drain_filter(|k, _| k > 16).collect();
+    drained.sort();
+    assert_eq!(drained, vec![(17, 18), (18, 19), (19, 20), (20, 21)]);
+    assert_eq!(map.drain_filter(|k, _| k == 2).next(), Some((2, 3)));
+    drop(map.drain_filter(|k, _| k == 4 || k == 7));
+    map.debug_assert_consistent();
+    assert_eq!(map.keys().max(), Some(16));
+    assert_eq!(map.len(), 13);
+}
+
+#[test]
+fn test_remove_many(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    assert_eq!(map.remove_many(&[20, 1, 3, 20, 100, 11]), 4);
+    map.debug_assert_consistent();
+    assert_eq!(map.len(), 16);
+    assert!(!map.contains(1) && !map.contains(3) && !map.contains(11) && !map.contains(20));
+    assert_eq!(map.get(19), Some(&19));
+    assert_eq!(map.remove_many(&[]), 0);
+}
+
+#[test]
+fn test_append(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i)).collect();
+    let mut other: SmallVecMap<u32, 4> = (5..16).map(|i| (i, i * 2)).collect();
+    map.append(&mut other);
+    assert!(other.is_empty());
+    assert_eq!(map.len(), 15);
+    assert_eq!(map.get(4), Some(&4));
+    assert_eq!(map.get(5), Some(&10));
+    assert_eq!(map.get(15), Some(&30));
+
+    let mut map: SmallVecMap<u32, 4> = (1..3).map(|i| (i, i)).collect();
+    let mut other: SmallVecMap<u32, 4> = SmallVecMap::with_capacity_and_max_key(4, 50);
+    other.insert(3, 3);
+    map.append(&mut other);
+    assert_eq!(map.fragmentation(), (1, 4));
+}
+
+#[test]
+fn test_merge_with(){
+    let mut map: SmallVecMap<u32, 4> = (5..16).map(|i| (i, i * 2)).collect();
+    map.merge_with((14..18).map(|i| (i, 1)).collect(), |e, v| *e += v);
+    assert_eq!(map.len(), 13);
+    assert_eq!(map.get(13), Some(&26));
+    assert_eq!(map.get(15), Some(&31));
+    assert_eq!(map.get(17), Some(&1));
+
+    let mut map: SmallVecMap<u32, 4> = (1..3).map(|i| (i, i)).collect();
+    let mut other: SmallVecMap<u32, 4> = SmallVecMap::with_capacity_and_max_key(4, 50);
+    other.insert(3, 3);
+    map.merge_with(other, |e, v| *e += v);
+    assert_eq!(map.fragmentation(), (1, 4));
+}
+
+#[test]
+fn test_pop(){
+    let mut map: SmallVecMap<u32, 4> = (1..11).map(|i| (i, i * 2)).collect();
+    let (k, v) = map.pop().unwrap();
+    assert_eq!(v, k * 2);
+    assert_eq!(map.get(k), None);
+    assert_eq!(map.len(), 9);
+    map.debug_assert_consistent();
+    assert!(SmallVecMap::<u32, 4>::new().pop().is_none());
+}
+
+#[test]
+fn test_shrink_to_fit(){
+    let mut map: SmallVecMap<u32, 4> = (1..1001).map(|i| (i, i)).collect();
+    for i in 11..1001{
+        map.remove(i);
+    }
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 11);
+    assert_eq!(map.len(), 10);
+    for i in 1..11{
+        assert_eq!(map.get(i), Some(&i));
+    }
+    assert_eq!(map.get(11), None);
+
+    map.clear();
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 0);
+}
+
+#[test]
+fn test_capacity(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert_eq!(map.entries_capacity(), 4);
+    assert_eq!(map.index_capacity(), map.capacity());
+    map.reserve(16);
+    assert!(map.index_capacity() >= 16);
+    map.extend((0..5).map(|i| (i, i)));
+    assert!(map.entries_capacity() >= 5);
+}
+
+#[test]
+fn test_is_spilled(){
+    let mut map: SmallVecMap<u32, 4> = (0..4).map(|i| (i, i)).collect();
+    assert!(!map.is_spilled());
+    map.insert(4, 4);
+    assert!(map.is_spilled());
+    map.remove(4);
+    assert!(map.is_spilled());
+    map.shrink_to_fit();
+    assert!(!map.is_spilled());
+}
+
+#[test]
+fn test_insert_would_grow(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert!(map.insert_would_grow(0));
+    map.reserve_exact(16);
+    assert!(!map.insert_would_grow(15));
+    assert!(map.insert_would_grow(1000));
+}
+
+#[test]
+fn test_try_reserve(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert!(map.try_reserve(16).is_ok());
+    assert!(map.index_capacity() >= 16);
+    assert!(map.entries_capacity() >= 16);
+    assert!(matches!(map.try_reserve(usize::MAX), Err(TryReserveError::Indexs(_))));
+}
+
+#[test]
+fn test_compact(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(7);
+    map.compact();
+    let keys: Vec<u32> = map.keys().collect();
+    assert_eq!(keys, (1..20).filter(|i| *i != 7).collect::<Vec<_>>());
+    for i in 1..20{
+        assert_eq!(map.get(i), if i == 7 { None } else { Some(&i) });
+    }
+}
+
+#[test]
+fn test_clone_compact(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(7);
+    let compacted = map.clone_compact();
+    compacted.debug_assert_consistent();
+    assert!(compacted == map);
+    assert_eq!(compacted.fragmentation(), map.fragmentation());
+    assert_eq!(compacted.keys().collect::<Vec<_>>(), (1..20).filter(|i| *i != 7).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_values_sorted(){
+    let mut map: SmallVecMap<u32, 4> = [(9, 90), (2, 20), (5, 50), (7, 70)].into_iter().collect();
+    map.remove(7);
+    assert_eq!(map.values_sorted().copied().collect::<Vec<_>>(), vec![20, 50, 90]);
+}
+
+#[test]
+fn test_max_key(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    map.remove(20);
+    assert_eq!(map.max_key(), Some(19));
+    assert_eq!(SmallVecMap::<u32, 4>::new().max_key(), None);
+}
+
+#[test]
+fn test_first_last_key_value(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    assert_eq!(map.first_key_value(), Some((1, &1)));
+    assert_eq!(map.last_key_value(), Some((19, &19)));
+    let empty: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert_eq!(empty.first_key_value(), None);
+    assert_eq!(empty.last_key_value(), None);
+}
+
+#[test]
+fn test_density(){
+    let mut map: SmallVecMap<u32, 4> = SmallVecMap::new();
+    assert_eq!(map.density(), 0.0);
+    assert!(map.is_contiguous());
+    map.extend((0..20).map(|i| (i, i)));
+    assert!(map.is_contiguous());
+    map.remove(7);
+    assert!(!map.is_contiguous());
+    assert_eq!(map.density(), 19.0 / 20.0);
+}
+
+#[test]
+fn test_should_compact(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(7);
+    assert!(map.should_compact(0.05));
+    assert!(!map.should_compact(0.1));
+    assert!(!SmallVecMap::<u32, 4>::new().should_compact(0.0));
+}
+
+#[test]
+fn test_fragmentation(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(7);
+    assert_eq!(map.fragmentation(), (2, 21));
+    assert_eq!(SmallVecMap::<u32, 4>::new().fragmentation(), (0, 0));
+}
+
+#[test]
+fn test_range(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(7);
+    let range: Vec<u32> = map.range(5..=9).map(|(k, _)| k).collect();
+    assert_eq!(range, vec![5, 6, 8, 9]);
+    assert_eq!(map.range(..3).count(), 2);
+    assert_eq!(map.range(30..).count(), 0);
+}
+
+#[test]
+fn test_iter_from(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(18);
+    let from: Vec<u32> = map.iter_from(16).map(|(k, _)| k).collect();
+    assert_eq!(from, vec![16, 17, 19]);
+    assert_eq!(map.iter_from(100).count(), 0);
+}
+
+#[test]
+fn test_iter_sorted(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).rev().map(|i| (i, i)).collect();
+    map.remove(20);
+    map.remove(7);
+    let sorted: Vec<(u32, u32)> = map.iter_sorted().map(|(k, v)| (k, *v)).collect();
+    assert_eq!(sorted, (1..20).filter(|i| *i != 7).map(|i| (i, i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_clone_with_capacity(){
+    let mut map: SmallVecMap<u32, 4> = (1..21).map(|i| (i, i)).collect();
+    map.remove(7);
+    assert!(map.is_spilled());
+    let wide = map.clone_with_capacity::<32>();
+    wide.debug_assert_consistent();
+    assert!(!wide.is_spilled());
+    assert_eq!(wide.iter_sorted().count(), 19);
+    assert!(wide.iter().eq(map.iter()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde(){
+    let mut map: SmallVecMap<String, 4> = SmallVecMap::new();
+    for i in [300, 2, 17, 9, 1000]{
+        map.insert(i, i.to_string());
+    }
+    map.remove(9);
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(json, r#"{"2":"2","17":"17","300":"300","1000":"1000"}"#);
+    let r: SmallVecMap<String, 4> = serde_json::from_str(&json).unwrap();
+    assert!(r == map);
+    assert_eq!(r.get(1000), Some(&"1000".to_string()));
+    assert_eq!(r.get(9), None);
+
+    let json = format!(r#"{{"{}":"x"}}"#, u32::null());
+    assert!(serde_json::from_str::<SmallVecMap<String, 4>>(&json).is_err());
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn test_borsh(){
+    let mut map: SmallVecMap<String, 4> = SmallVecMap::new();
+    for i in [300, 2, 17, 9, 1000]{
+        map.insert(i, i.to_string());
+    }
+    map.remove(9);
+    let bytes = borsh::to_vec(&map).unwrap();
+    let r: SmallVecMap<String, 4> = borsh::from_slice(&bytes).unwrap();
+    assert!(r == map);
+    assert_eq!(r.get(1000), Some(&"1000".to_string()));
+    assert_eq!(r.get(9), None);
+}
+
+#[cfg(feature = "rkyv")]
+#[test]
+fn test_rkyv(){
+    let mut map: SmallVecMap<String, 4> = SmallVecMap::new();
+    for i in [300, 2, 17, 9, 1000]{
+        map.insert(i, i.to_string());
+    }
+    map.remove(9);
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&map).unwrap();
+    let archived = rkyv::access::<<SmallVecMap<String, 4> as rkyv::Archive>::Archived, rkyv::rancor::Error>(&bytes).unwrap();
+    assert_eq!(archived.len(), 4);
+    assert_eq!(archived.get(&17.into()).map(|v| v.as_str()), Some("17"));
+    assert!(archived.get(&9.into()).is_none());
+
+    let r: SmallVecMap<String, 4> = rkyv::deserialize::<_, rkyv::rancor::Error>(archived).unwrap();
+    assert!(r == map);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_iter(){
+    use rayon::prelude::*;
+    let mut map: SmallVecMap<u64, 4> = (1..1001).map(|i| (i, 0)).collect();
+    map.remove(500);
+    map.par_iter_mut().for_each(|(k, v)| *v = k as u64 * 2);
+    let sum: u64 = map.par_iter().map(|(k, v)| {
+        assert_eq!(*v, k as u64 * 2);
+        *v
+    }).sum();
+    assert_eq!(sum, 1000 * 1001 - 1000);
 }
 
 // #[test]
@@ -413,4 +2574,101 @@ fn test(){
 // fn m(){
 //     //let a: usize = (usize::max_value() - 1) << 1;
 //     println!("xxxxxxxxxxxxxxxxxxxxxx");
-// }
\ No newline at end of file
+// }
+
+#[test]
+fn test_u64_key() {
+    let mut map: SmallVecMap<&str, 4, u64> = SmallVecMap::new();
+    map.insert(5u64, "a");
+    map.insert(300, "b");
+    map.insert(2, "c");
+    assert_eq!(map.get(300), Some(&"b"));
+    assert_eq!(map.remove(5), Some("a"));
+    assert_eq!(Map::get(&map, &2), Some(&"c"));
+    let sorted: Vec<(u64, &str)> = map.iter_sorted().map(|(k, v)| (k, *v)).collect();
+    assert_eq!(sorted, vec![(2, "c"), (300, "b")]);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_u64_key_index() {
+    assert_eq!(MapKey::index(300u64), 300);
+    assert_eq!(<u64 as MapKey>::from_index(300), 300);
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!(MapKey::index(1u64 << 40), 1usize << 40);
+}
+
+#[test]
+#[cfg(target_pointer_width = "32")]
+#[should_panic(expected = "SmallVecMap key exceeds usize")]
+fn test_u64_key_exceeds_usize() {
+    MapKey::index(1u64 << 40);
+}
+
+#[test]
+fn test_trim_tail() {
+    fn new_map() -> SmallVecMap<u32, 4> {
+        (0..10u32).map(|i| (i, i)).collect()
+    }
+    let mut map = new_map();
+    map.remove(9);
+    assert_eq!(map.status(9), Slot::OutOfRange);
+    assert_eq!(map.fragmentation(), (0, 9));
+
+    let mut map = new_map();
+    assert_eq!(map.pop(), Some((9, 9)));
+    assert_eq!(map.status(9), Slot::OutOfRange);
+
+    let mut map = new_map();
+    map.retain(|k, _| k < 6 && k != 3);
+    assert_eq!(map.status(6), Slot::OutOfRange);
+    assert_eq!(map.status(3), Slot::Vacant);
+
+    let mut map = new_map();
+    assert_eq!(map.remove_many(&[8, 9, 2]), 3);
+    assert_eq!(map.status(8), Slot::OutOfRange);
+    assert_eq!(map.status(7), Slot::Occupied);
+
+    let mut map = new_map();
+    assert_eq!(map.drain_filter(|k, _| k >= 7).count(), 3);
+    assert_eq!(map.status(7), Slot::OutOfRange);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_usize_key_bounds() {
+    let mut map: SmallVecMap<&str, 4, usize> = SmallVecMap::new();
+    map.insert(3, "a");
+    map.insert(9, "b");
+    assert_eq!(map.range(..=usize::MAX).count(), 2);
+    assert_eq!(map.range((Bound::Excluded(usize::MAX), Bound::Unbounded)).count(), 0);
+    map.truncate_keys(usize::MAX);
+    assert_eq!(map.len(), 2);
+    map.debug_assert_consistent();
+}
+
+#[test]
+fn test_align() {
+    #[derive(Debug, PartialEq)]
+    #[repr(align(16))]
+    struct Align16(u8);
+
+    let mut map: SmallVecMap<Align16, 2> = SmallVecMap::default();
+    for i in 0..8u32 {
+        map.insert(i * 3, Align16(i as u8));
+        for (_, v) in map.iter() {
+            assert_eq!(v as *const Align16 as usize % 16, 0);
+        }
+    }
+    for i in 0..8u32 {
+        if i % 2 == 0 {
+            assert_eq!(map.remove(i * 3), Some(Align16(i as u8)));
+        }
+    }
+    map.shrink_to_fit();
+    assert_eq!(map.len(), 4);
+    for (k, v) in map.iter() {
+        assert_eq!(v.0 as u32 * 3, k);
+        assert_eq!(v as *const Align16 as usize % 16, 0);
+    }
+}
@@ -15,10 +15,10 @@
 //!
 //! 再决定使用VecMap前，你应该综合考虑这几个问题：访问性能、数据连续性、内存的浪费情况。
 //!
-use std::mem::{replace};
 use std::fmt::Debug;
 use std::ops::{Index, IndexMut};
-use std::slice::Iter;
+use std::iter::Enumerate;
+use std::slice;
 // use std::ops::Drop;
 // use std::ptr::write;
 
@@ -100,7 +100,7 @@ impl<T> VecMap<T> {
     }
 
     /// 获取一个只读迭代器
-    pub fn iter(&self) -> Iter<Option<T>> {
+    pub fn iter(&self) -> slice::Iter<'_, Option<T>> {
         self.entries.iter()
     }
 
@@ -116,14 +116,17 @@ impl<T> VecMap<T> {
     
     /// 替换指定位置的值, 并返回旧值
     /// 你应该确认，旧值一定存在，否则将会panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
     pub unsafe fn replace(&mut self, index: usize, value: T) -> T {
-        replace(&mut self.entries[index], Some(value)).unwrap()
+        self.entries[index].replace(value).unwrap()
     }
 
 
     /// 取到某个偏移位置的只读值
     pub fn get(&self, index: usize) -> Option<&T> {
-        if index == usize::max_value() || index >= self.entries.len(){
+        if index == usize::MAX || index >= self.entries.len(){
             return None;
         }
         match &self.entries[index] {
@@ -134,7 +137,7 @@ impl<T> VecMap<T> {
 
     /// 取到某个偏移位置的可变值
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index == usize::max_value() || index >= self.entries.len(){
+        if index == usize::MAX || index >= self.entries.len(){
             return None;
         }
         match &mut self.entries[index] {
@@ -143,14 +146,36 @@ impl<T> VecMap<T> {
         }
     }
 
+    /// 同时取到两个位置的可变值，位置不存在值或两个位置相等时，对应位置返回None
+    pub fn get2_mut(&mut self, a: usize, b: usize) -> (Option<&mut T>, Option<&mut T>) {
+        if a == b {
+            return (None, None);
+        }
+        match (self.contains(a), self.contains(b)) {
+            (true, true) => {
+                let [a, b] = self.entries.get_disjoint_mut([a, b]).unwrap();
+                (a.as_mut(), b.as_mut())
+            }
+            (true, false) => (self.get_mut(a), None),
+            (false, true) => (None, self.get_mut(b)),
+            (false, false) => (None, None),
+        }
+    }
+
     /// 取到某个偏移位置的只读值
     /// 如果该位置不存在值，将panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
     pub unsafe fn get_unchecked(&self, index: usize) -> &T {
         self.entries[index].as_ref().unwrap()
     }
 
     /// 取到某个偏移位置的可变值
     /// 如果该位置不存在值，将panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
     pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut T {
         self.entries[index].as_mut().unwrap()
     }
@@ -165,9 +190,9 @@ impl<T> VecMap<T> {
 		} else if index >= len {
 			self.entries.extend((0..index - len + 1).map(|_| None));
             self.len += 1;
-            replace(&mut self.entries[index], Some(val))
+            self.entries[index].replace(val)
         }else {
-            let r = replace(&mut self.entries[index], Some(val));
+            let r = self.entries[index].replace(val);
             if r.is_none(){
                 self.len += 1;
             }
@@ -180,7 +205,7 @@ impl<T> VecMap<T> {
         if index >= self.entries.len() {
             return None;
         }
-        match replace(&mut self.entries[index], None) {
+        match self.entries[index].take() {
             Some(v) => {
                 self.len -= 1;
                 Some(v)
@@ -190,14 +215,29 @@ impl<T> VecMap<T> {
     }
 
     /// 移除指定位置的值，返回被移除的值，如果该位置不存在一个值将panic
+    ///
+    /// # Safety
+    /// 调用者须保证`index`位置存在值
     pub unsafe fn remove_unchecked(&mut self, index: usize) -> T {
         self.len -= 1;
-        replace(&mut self.entries[index], None).unwrap()
+        self.entries[index].take().unwrap()
+    }
+
+    /// 只保留`f`返回true的值
+    pub fn retain<F: FnMut(usize, &mut T) -> bool>(&mut self, mut f: F) {
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            if let Some(v) = entry {
+                if !f(i, v) {
+                    *entry = None;
+                    self.len -= 1;
+                }
+            }
+        }
     }
 
     /// 判断指定位置是否存在一个值
     pub fn contains(&self, index: usize) -> bool {
-        if index == usize::max_value() || index >= self.entries.len(){
+        if index == usize::MAX || index >= self.entries.len(){
             return false;
         }
         match &self.entries[index] {
@@ -217,6 +257,13 @@ impl<T> VecMap<T> {
 impl<T> Map for VecMap<T> {
 	type Key = usize;
 	type Val = T;
+    type Iter<'a> = Iter<'a, T> where Self: 'a;
+
+    #[inline]
+    fn iter(&self) -> Iter<'_, T> {
+        Iter(self.entries.iter().enumerate())
+    }
+
     #[inline]
     fn get(&self, key: &usize) -> Option<&T> {
         self.get(*key)
@@ -227,6 +274,11 @@ impl<T> Map for VecMap<T> {
         self.get_mut(*key)
     }
 
+    #[inline]
+    fn get2_mut(&mut self, a: &usize, b: &usize) -> (Option<&mut T>, Option<&mut T>) {
+        self.get2_mut(*a, *b)
+    }
+
     #[inline]
     unsafe fn get_unchecked(&self, key: &usize) -> &T {
         self.get_unchecked(*key)
@@ -252,6 +304,21 @@ impl<T> Map for VecMap<T> {
         self.remove(*key)
     }
 
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.reserve(additional)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    #[inline]
+    fn retain(&mut self, mut f: impl FnMut(&usize, &mut T) -> bool) {
+        self.retain(|k, v| f(&k, v))
+    }
+
     #[inline]
     fn contains(&self, key: &usize) -> bool {
         self.contains(*key)
@@ -275,6 +342,23 @@ impl<T> Map for VecMap<T> {
 	}
 }
 
+/// VecMap的只读迭代器，跳过空位，产出`(key, &value)`
+pub struct Iter<'a, T>(Enumerate<slice::Iter<'a, Option<T>>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (usize, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        self.0.by_ref().find_map(|(k, v)| v.as_ref().map(|v| (k, v)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
 impl<T> Index<usize> for VecMap<T> {
     type Output = T;
 
@@ -289,6 +373,43 @@ impl<T> IndexMut<usize> for VecMap<T> {
     }
 }
 
+/// 序列化为`key -> value`的映射，按键升序输出
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for VecMap<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entries.iter().enumerate().filter_map(|(k, v)| v.as_ref().map(|v| (k, v))))
+    }
+}
+
+/// 从`key -> value`的映射反序列化
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for VecMap<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<T>(std::marker::PhantomData<T>);
+
+        impl<'de, T: serde::Deserialize<'de>> serde::de::Visitor<'de> for MapVisitor<T> {
+            type Value = VecMap<T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of usize keys")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut map = VecMap::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some((k, v)) = access.next_entry::<usize, T>()? {
+                    if k == usize::MAX {
+                        return Err(serde::de::Error::custom("VecMap key can not be usize::MAX"));
+                    }
+                    map.insert(k, v);
+                }
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor(std::marker::PhantomData))
+    }
+}
+
 // impl<'a, T> IntoIterator for &'a Slab<T> {
 //     type Item = (usize, &'a T);
 //     type IntoIter = SlabIter<'a, T>;
@@ -356,7 +477,7 @@ impl<T> IndexMut<usize> for VecMap<T> {
 //         let mut sign_index1 = self.curr_index%usize_size();
 //         for i in sign_index..self.signs.len(){
 //             let sign = self.signs[i].clone() >> sign_index1;
-//             if sign != usize::max_value() >> sign_index1{
+//             if sign != usize::MAX >> sign_index1{
 //                 let first_zero = find_zero(sign);
 //                 let curr = self.curr_index + first_zero;
 //                 self.curr_index = curr + 1;
@@ -384,7 +505,7 @@ impl<T> IndexMut<usize> for VecMap<T> {
 //         let mut sign_index1 = self.curr_index%usize_size();
 //         for i in sign_index..self.signs.len(){
 //             let sign = self.signs[i].clone() >> sign_index1;
-//             if sign != usize::max_value() >> sign_index1{
+//             if sign != usize::MAX >> sign_index1{
 //                 let first_zero = find_zero(sign);
 //                 let curr = self.curr_index + first_zero;
 //                 self.curr_index = curr + 1;
@@ -478,6 +599,110 @@ fn test(){
     assert_eq!(unsafe{map.get_unchecked_mut(7)}, &mut 7);
 }
 
+#[test]
+fn test_retain(){
+    let mut map: VecMap<u64> = VecMap::new();
+    for i in 1..11{
+        map.insert(i, i as u64);
+    }
+    Map::retain(&mut map, |k, v| {
+        *v += 1;
+        k % 2 == 0
+    });
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.get(2), Some(&3));
+    assert_eq!(map.get(3), None);
+}
+
+#[test]
+fn test_iter(){
+    let mut map: VecMap<u64> = VecMap::new();
+    for i in 1..6{
+        map.insert(i, i as u64 * 10);
+    }
+    map.remove(3);
+    let r: Vec<(usize, &u64)> = Map::iter(&map).collect();
+    assert_eq!(r, vec![(1, &10), (2, &20), (4, &40), (5, &50)]);
+}
+
+#[test]
+fn test_keys_values(){
+    let mut map: VecMap<u64> = VecMap::new();
+    for i in [2, 4, 6]{
+        map.insert(i, i as u64 + 1);
+    }
+    assert_eq!(Map::keys(&map).collect::<Vec<_>>(), vec![2, 4, 6]);
+    assert_eq!(Map::values(&map).sum::<u64>(), 15);
+}
+
+#[test]
+fn test_extend(){
+    let mut map: VecMap<u64> = VecMap::new();
+    map.insert(2, 3);
+    Map::extend(&mut map, [(1, 1), (2, 2), (5, 5)]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(2), Some(&2));
+    assert_eq!(map.get(5), Some(&5));
+}
+
+#[test]
+fn test_clear(){
+    let mut map: VecMap<u64> = VecMap::new();
+    Map::extend(&mut map, [(1, 1), (2, 2), (5, 5)]);
+    Map::clear(&mut map);
+    assert!(Map::is_empty(&map));
+    assert_eq!(map.get(2), None);
+    assert_eq!(Map::iter(&map).count(), 0);
+}
+
+#[test]
+fn test_reserve(){
+    let mut map: VecMap<u64> = VecMap::new();
+    Map::reserve(&mut map, 100);
+    assert!(Map::capacity(&map) >= 100);
+}
+
+#[test]
+fn test_get_or_insert_with(){
+    let mut map: VecMap<u64> = VecMap::new();
+    *Map::get_or_insert_with(&mut map, 3, || 0) += 1;
+    *Map::get_or_insert_with(&mut map, 3, || 0) += 1;
+    assert_eq!(map.get(3), Some(&2));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_get2_mut(){
+    let mut map: VecMap<u64> = VecMap::new();
+    for i in 1..4{
+        map.insert(i, i as u64);
+    }
+    let (a, b) = Map::get2_mut(&mut map, &1, &2);
+    std::mem::swap(a.unwrap(), b.unwrap());
+    assert_eq!(map.get(1), Some(&2));
+    assert_eq!(Map::get2_mut(&mut map, &1, &5), (Some(&mut 2), None));
+    assert_eq!(Map::get2_mut(&mut map, &3, &3), (None, None));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde(){
+    let mut map: VecMap<String> = VecMap::default();
+    for i in [30, 2, 7, 0]{
+        map.insert(i, i.to_string());
+    }
+    map.remove(7);
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(json, r#"{"0":"0","2":"2","30":"30"}"#);
+    let r: VecMap<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(r.len(), 3);
+    assert_eq!(r.get(30), Some(&"30".to_string()));
+    assert_eq!(r.get(7), None);
+
+    let json = format!(r#"{{"{}":"x"}}"#, usize::MAX);
+    assert!(serde_json::from_str::<VecMap<String>>(&json).is_err());
+}
+
 // #[test]
 // fn test_eff(){
     
@@ -508,6 +733,6 @@ fn test(){
 
 // #[test]
 // fn m(){
-//     //let a: usize = (usize::max_value() - 1) << 1;
+//     //let a: usize = (usize::MAX - 1) << 1;
 //     println!("xxxxxxxxxxxxxxxxxxxxxx");
 // }
\ No newline at end of file